@@ -0,0 +1,250 @@
+use crate::{BitStream, DecodePolicy, Decoder, DecoderError, Format, OwnedDecoderOutput};
+use serde_json::{json, Value};
+use std::cmp::Reverse;
+use tracing::debug;
+
+/// A decoder that learns which formats a reader produces.
+///
+/// A given reader is consistent: one model always inverts, another is always
+/// MSB-first. The adaptive decoder counts which format decoded each swipe
+/// and tries the most successful formats first, so the reader's own format
+/// is reached without trying the others, and a loose format is not taken
+/// before it.
+///
+/// The learned profile can be saved as JSON with
+/// [`AdaptiveDecoder::export_profile`] and restored with
+/// [`AdaptiveDecoder::import_profile`].
+///
+/// # Example
+///
+/// ```
+/// use magstripe_rs::{AdaptiveDecoder, BitStream, Format};
+///
+/// let data = vec![
+///     255, 255, 255, 151, 222, 246, 253, 190, 141, 247, 7, 127, 255, 255, 255, 255, 192,
+/// ];
+/// let mut decoder = AdaptiveDecoder::new(Format::FINANCIAL);
+/// decoder.decode(BitStream::new(&data, 130).unwrap()).unwrap();
+/// assert_eq!(decoder.formats()[0], Format::Track2Inverted);
+///
+/// let profile = decoder.export_profile();
+/// let mut restarted = AdaptiveDecoder::new(Format::FINANCIAL);
+/// restarted.import_profile(&profile).unwrap();
+/// assert_eq!(restarted.formats()[0], Format::Track2Inverted);
+/// ```
+#[derive(Debug, Clone)]
+pub struct AdaptiveDecoder {
+    /// The formats in the order they are attempted
+    formats: Vec<Format>,
+    /// Statistics for each format, in the same order
+    stats: Vec<FormatStats>,
+    try_reverse: bool,
+    policy: DecodePolicy,
+    swipes: u64,
+}
+
+/// What has been learned about a single format
+#[derive(Debug, Clone)]
+struct FormatStats {
+    /// Position of the format in the configured list, which breaks ties
+    index: usize,
+    /// The name the format is stored under in a profile, unique in the list
+    key: String,
+    /// The number of swipes the format decoded
+    successes: u64,
+}
+
+impl AdaptiveDecoder {
+    /// Create an adaptive decoder that starts with the given format order
+    ///
+    /// Like [`Decoder::new`], reversed swipes are tried and the standard
+    /// policy is used.
+    pub fn new(formats: impl Into<Vec<Format>>) -> Self {
+        let formats = formats.into();
+        let mut stats: Vec<FormatStats> = Vec::with_capacity(formats.len());
+        for (index, format) in formats.iter().enumerate() {
+            let mut key = profile_key(format, index);
+            if stats.iter().any(|earlier| earlier.key == key) {
+                key = format!("{}#{}", key, index);
+            }
+            stats.push(FormatStats {
+                index,
+                key,
+                successes: 0,
+            });
+        }
+        Self {
+            formats,
+            stats,
+            try_reverse: true,
+            policy: DecodePolicy::default(),
+            swipes: 0,
+        }
+    }
+
+    /// Enable or disable decoding of backwards swipes, see
+    /// [`Decoder::with_reverse`]
+    pub fn with_reverse(mut self, try_reverse: bool) -> Self {
+        self.try_reverse = try_reverse;
+        self
+    }
+
+    /// Set how strictly bitstreams are held to their format
+    pub fn with_policy(mut self, policy: DecodePolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// The formats in the order they are currently attempted
+    pub fn formats(&self) -> &[Format] {
+        &self.formats
+    }
+
+    /// The number of swipes decoded with `format`
+    pub fn successes(&self, format: &Format) -> u64 {
+        self.formats
+            .iter()
+            .position(|f| f == format)
+            .map_or(0, |i| self.stats[i].successes)
+    }
+
+    /// The number of swipes seen, including those that failed to decode
+    pub fn swipes(&self) -> u64 {
+        self.swipes
+    }
+
+    /// Decode a bitstream, trying the most successful formats first, and
+    /// learn from the result
+    pub fn decode(&mut self, stream: BitStream) -> Result<OwnedDecoderOutput, DecoderError> {
+        self.swipes += 1;
+        let result = Decoder::new(&self.formats)
+            .with_reverse(self.try_reverse)
+            .with_policy(self.policy)
+            .decode(stream)
+            .map(|output| {
+                let position = self
+                    .formats
+                    .iter()
+                    .position(|format| std::ptr::eq(format, output.format))
+                    .expect("the output format comes from the list");
+                (position, output.into_owned())
+            });
+
+        let (position, output) = result?;
+        self.stats[position].successes += 1;
+        self.reorder();
+        Ok(output)
+    }
+
+    /// Forget everything learned, returning to the configured order
+    pub fn reset(&mut self) {
+        self.swipes = 0;
+        for stats in &mut self.stats {
+            stats.successes = 0;
+        }
+        self.reorder();
+    }
+
+    /// Export the learned profile as JSON
+    ///
+    /// Named formats are identified by their variant name, such as
+    /// `"Track2Inverted"`, so their counts carry over to any decoder
+    /// configured with them. [`Format::Encoded`], [`Format::Custom`] and
+    /// [`Format::User`] formats are identified by their kind and position in
+    /// the configured list, such as `"Custom#3"`, so their counts only carry
+    /// over to a decoder configured with the same list. A named format listed
+    /// more than once is identified by its name the first time and by its
+    /// name and position after that, such as `"Track2#1"`.
+    pub fn export_profile(&self) -> String {
+        let formats: Vec<Value> = self
+            .stats
+            .iter()
+            .map(|stats| {
+                json!({
+                    "format": stats.key,
+                    "successes": stats.successes,
+                })
+            })
+            .collect();
+        json!({ "swipes": self.swipes, "formats": formats }).to_string()
+    }
+
+    /// Import a profile exported by [`AdaptiveDecoder::export_profile`]
+    ///
+    /// The counts in the profile replace those learned so far. Formats in the
+    /// profile that this decoder is not configured with are skipped and
+    /// their names returned, and configured formats missing from the profile
+    /// start from zero.
+    ///
+    /// Returns [`DecoderError::InvalidProfile`] if the JSON is not a profile;
+    /// the decoder is unchanged in that case.
+    pub fn import_profile(&mut self, profile: &str) -> Result<Vec<String>, DecoderError> {
+        let invalid = |reason: &str| DecoderError::InvalidProfile {
+            reason: reason.to_string(),
+        };
+        let profile: Value =
+            serde_json::from_str(profile).map_err(|error| invalid(&error.to_string()))?;
+        let swipes = profile["swipes"]
+            .as_u64()
+            .ok_or_else(|| invalid("missing swipe count"))?;
+        let entries = profile["formats"]
+            .as_array()
+            .ok_or_else(|| invalid("missing format list"))?;
+
+        let mut successes = vec![0; self.formats.len()];
+        let mut unmatched = Vec::new();
+        for entry in entries {
+            let (Some(key), Some(count)) = (entry["format"].as_str(), entry["successes"].as_u64())
+            else {
+                return Err(invalid("format entries need a format and a success count"));
+            };
+            let position = self.stats.iter().position(|stats| stats.key == key);
+            match position {
+                Some(position) => successes[position] = count,
+                None => {
+                    debug!("Skipping unknown format in profile: {}", key);
+                    unmatched.push(key.to_string());
+                }
+            }
+        }
+
+        self.swipes = swipes;
+        for (stats, count) in self.stats.iter_mut().zip(successes) {
+            stats.successes = count;
+        }
+        self.reorder();
+        Ok(unmatched)
+    }
+
+    /// Sort the formats by success, keeping the configured order for ties
+    fn reorder(&mut self) {
+        let mut entries: Vec<(Format, FormatStats)> =
+            self.formats.drain(..).zip(self.stats.drain(..)).collect();
+        entries.sort_by_key(|(_, stats)| (Reverse(stats.successes), stats.index));
+        (self.formats, self.stats) = entries.into_iter().unzip();
+    }
+}
+
+/// The name a format is stored under in an exported profile, given its
+/// position in the configured list
+///
+/// The names of the named variants are part of the profile format and must
+/// not change.
+fn profile_key(format: &Format, index: usize) -> String {
+    let name = match format {
+        Format::Track2 => "Track2",
+        Format::Track2Inverted => "Track2Inverted",
+        Format::Track2MSB => "Track2MSB",
+        Format::Track2LSB => "Track2LSB",
+        Format::Track2Raw => "Track2Raw",
+        Format::Track2SwappedParity => "Track2SwappedParity",
+        Format::Track2EvenParity => "Track2EvenParity",
+        Format::Track1 => "Track1",
+        Format::Track1Inverted => "Track1Inverted",
+        Format::Track3 => "Track3",
+        Format::Encoded(..) => return format!("Encoded#{}", index),
+        Format::Custom(_) => return format!("Custom#{}", index),
+        Format::User(_) => return format!("User#{}", index),
+    };
+    name.to_string()
+}
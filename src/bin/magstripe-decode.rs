@@ -1,7 +1,7 @@
 #![allow(clippy::uninlined_format_args)]
 
 use clap::Parser;
-use magstripe_rs::{BitStream, Decoder, Format};
+use magstripe_rs::{BitStream, DecodePolicy, Decoder, Format, Track};
 use std::process;
 use tracing::{error, info};
 use tracing_subscriber::EnvFilter;
@@ -14,7 +14,8 @@ struct Args {
     #[arg(value_name = "BYTES")]
     bytes: String,
 
-    /// Number of bits to decode (defaults to 8 * number of bytes)
+    /// Number of bits to decode (defaults to the end of the trailing clock
+    /// bits, ignoring junk in the final byte)
     #[arg(short, long)]
     bits: Option<usize>,
 
@@ -22,13 +23,72 @@ struct Args {
     #[arg(short, long)]
     verbose: bool,
 
-    /// Try all known formats (by default, tries common ones)
+    /// Try all known formats, the same as `--preset all`
     #[arg(short = 'a', long)]
     all_formats: bool,
 
+    /// Which set of formats to try
+    #[arg(long, value_enum, default_value = "financial")]
+    preset: PresetArg,
+
     /// Only try specific format(s), can be specified multiple times
     #[arg(short = 'f', long, value_enum)]
     format: Vec<FormatArg>,
+
+    /// How to print the decoded data (auto prints hex for binary payloads)
+    #[arg(short = 'o', long, value_enum, default_value = "auto")]
+    output: OutputArg,
+
+    /// How strictly the data is held to its format
+    #[arg(short = 'p', long, value_enum, default_value = "standard")]
+    policy: PolicyArg,
+
+    /// Rebuild a single damaged character from the LRC
+    #[arg(short = 'c', long)]
+    correct: bool,
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum PolicyArg {
+    Strict,
+    Standard,
+    Lenient,
+}
+
+impl PolicyArg {
+    fn to_policy(self) -> DecodePolicy {
+        match self {
+            PolicyArg::Strict => DecodePolicy::strict(),
+            PolicyArg::Standard => DecodePolicy::standard(),
+            PolicyArg::Lenient => DecodePolicy::lenient(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum PresetArg {
+    Financial,
+    Iso,
+    All,
+    IdCards,
+}
+
+impl PresetArg {
+    fn formats(self) -> &'static [Format] {
+        match self {
+            PresetArg::Financial => Format::FINANCIAL,
+            PresetArg::Iso => Format::ALL_ISO,
+            PresetArg::All => Format::ALL_VARIANTS,
+            PresetArg::IdCards => Format::ID_CARDS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum OutputArg {
+    Auto,
+    Text,
+    Hex,
 }
 
 #[derive(Debug, Clone, clap::ValueEnum)]
@@ -62,6 +122,14 @@ impl FormatArg {
     }
 }
 
+fn to_hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 fn parse_bytes(input: &str) -> Result<Vec<u8>, String> {
     // Remove whitespace and brackets
     let cleaned = input.trim().trim_start_matches('[').trim_end_matches(']');
@@ -114,88 +182,110 @@ fn main() {
         }
     };
 
-    // Determine bit count
-    let bit_count = args.bits.unwrap_or(bytes.len() * 8);
-
-    if bit_count > bytes.len() * 8 {
-        eprintln!(
-            "Error: Bit count {} exceeds available bits {}",
-            bit_count,
-            bytes.len() * 8
-        );
-        process::exit(1);
-    }
-
-    info!("Decoding {} bits from {} bytes", bit_count, bytes.len());
-
-    // Create bitstream
-    let stream = match BitStream::new(&bytes, bit_count) {
-        Ok(s) => s,
-        Err(e) => {
-            eprintln!("Error creating bitstream: {:?}", e);
-            process::exit(1);
+    // Create bitstream, finding where the data ends unless told the bit count
+    let stream = match args.bits {
+        Some(bit_count) => {
+            if bit_count > bytes.len() * 8 {
+                eprintln!(
+                    "Error: Bit count {} exceeds available bits {}",
+                    bit_count,
+                    bytes.len() * 8
+                );
+                process::exit(1);
+            }
+            match BitStream::new(&bytes, bit_count) {
+                Ok(s) => s,
+                Err(e) => {
+                    eprintln!("Error creating bitstream: {:?}", e);
+                    process::exit(1);
+                }
+            }
         }
+        None => BitStream::from_raw(&bytes),
     };
 
+    info!("Decoding {} bits from {} bytes", stream.len(), bytes.len());
+    info!("Data window: bits {:?}", stream.data_window());
+
     // Determine which formats to try
     let formats: Vec<Format> = if !args.format.is_empty() {
         // Use specific formats requested
         args.format.iter().map(|f| f.to_format()).collect()
     } else if args.all_formats {
-        // Try all known formats
-        vec![
-            Format::Track2,
-            Format::Track2Inverted,
-            Format::Track2MSB,
-            Format::Track2LSB,
-            Format::Track2Raw,
-            Format::Track2SwappedParity,
-            Format::Track2EvenParity,
-            Format::Track1,
-            Format::Track1Inverted,
-            Format::Track3,
-        ]
+        Format::ALL_VARIANTS.to_vec()
     } else {
-        // Default: try most common formats
-        vec![
-            Format::Track2,
-            Format::Track2Inverted,
-            Format::Track1,
-            Format::Track1Inverted,
-        ]
+        args.preset.formats().to_vec()
     };
 
     info!("Trying {} format(s)", formats.len());
 
     // Create decoder and decode
-    let decoder = Decoder::new(&formats);
+    let policy = args.policy.to_policy().with_correction(args.correct);
+    let decoder = Decoder::new(&formats).with_policy(policy);
 
     match decoder.decode(stream) {
         Ok(output) => {
             println!("\n✓ Successfully decoded!");
             println!("Format: {:?}", output.format);
-            println!("Data:   {}", output.data);
+            println!("Swipe:  {:?}", output.direction);
+            println!("Quality: {:.2}", output.quality.confidence);
+            match (args.output, output.text()) {
+                (OutputArg::Auto, Some(text)) => println!("Data:   {}", text),
+                (OutputArg::Text, _) => println!("Data:   {}", output.data),
+                (OutputArg::Auto | OutputArg::Hex, _) => {
+                    println!("Data:   {} (hex)", to_hex(&output.bytes))
+                }
+            }
+
+            if !output.metadata.corrected.is_empty() {
+                println!("Corrected characters: {:?}", output.metadata.corrected);
+            }
+            if !output.errors.is_empty() {
+                println!("\n{} character(s) could not be read:", output.errors.len());
+                for error in &output.errors {
+                    println!("  {}", error);
+                }
+            }
 
             if args.verbose {
                 println!("\nFormat details:");
-                match output.format {
-                    Format::Track2
-                    | Format::Track2Inverted
-                    | Format::Track2MSB
-                    | Format::Track2LSB
-                    | Format::Track2Raw
-                    | Format::Track2SwappedParity
-                    | Format::Track2EvenParity
-                    | Format::Track3 => {
+                match output.format.encoding() {
+                    Some((Track::Track2 | Track::Track3, encoding)) => {
                         println!("  Encoding: 5-bit (4 data + 1 parity)");
                         println!("  Character set: 0-9, :, ;, <, =, >, ?");
+                        println!("  Options: {:?}", encoding);
                     }
-                    Format::Track1 | Format::Track1Inverted => {
+                    Some((Track::Track1, encoding)) => {
                         println!("  Encoding: 7-bit (6 data + 1 parity)");
                         println!("  Character set: Alphanumeric (64 characters)");
+                        println!("  Options: {:?}", encoding);
                     }
-                    _ => {}
+                    None => {}
                 }
+
+                let metadata = &output.metadata;
+                println!("\nBitstream details:");
+                println!("  Bits used: {:?}", metadata.bit_range);
+                println!("  Start sentinel at: {:?}", metadata.start_sentinel_offset);
+                println!("  End sentinel at: {:?}", metadata.end_sentinel_offset);
+                println!(
+                    "  Check character: {}",
+                    if metadata.integrity_checked {
+                        "verified"
+                    } else if metadata.integrity_present {
+                        "present, not verified"
+                    } else {
+                        "absent"
+                    }
+                );
+                println!(
+                    "  Clock bits: {} leading, {} trailing",
+                    metadata.leading_clock_bits, metadata.trailing_clock_bits
+                );
+                println!(
+                    "  Skew before sentinel: {} bits",
+                    output.quality.sentinel_skew
+                );
             }
         }
         Err(e) => {
@@ -0,0 +1,305 @@
+use crate::{BitStream, BitStreamError};
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+
+/// An owned, growable sequence of bits.
+///
+/// `BitBuf` is the owned counterpart of [`BitStream`]: bits can be pushed,
+/// inserted and removed, and whole buffers sliced, reversed, inverted and
+/// concatenated without copying bytes by hand. It borrows as a `BitStream`
+/// for decoding with [`BitBuf::as_stream`], which costs nothing.
+///
+/// The bits are stored in the same left-aligned, MSB-first layout as a
+/// `BitStream`, with the unused bits of the final byte zeroed.
+///
+/// # Example
+///
+/// ```
+/// use magstripe_rs::{BitBuf, Decoder, Format};
+///
+/// // Clock bits, then ";1?" and its LRC as Track 2 characters
+/// let mut bits: BitBuf = "0000000000 11010 10000 11111 10101 0000000000".parse().unwrap();
+/// let formats = [Format::Track2, Format::Track2Inverted];
+/// let decoder = Decoder::new(&formats);
+/// assert_eq!(decoder.decode(bits.as_stream()).unwrap().data, "1");
+///
+/// bits.invert();
+/// let output = decoder.decode(bits.as_stream()).unwrap();
+/// assert_eq!(output.format, &Format::Track2Inverted);
+/// ```
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+pub struct BitBuf {
+    buffer: Vec<u8>,
+    bit_count: usize,
+}
+
+impl BitBuf {
+    /// Creates an empty buffer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty buffer with room for at least `bits` bits.
+    pub fn with_capacity(bits: usize) -> Self {
+        Self {
+            buffer: Vec::with_capacity(bits.div_ceil(8)),
+            bit_count: 0,
+        }
+    }
+
+    /// Creates a buffer holding a copy of the bits of a stream.
+    pub fn from_stream(stream: &BitStream) -> Self {
+        let mut bits = Self::with_capacity(stream.len());
+        bits.extend_from_stream(stream);
+        bits
+    }
+
+    /// Concatenates streams into a single buffer.
+    pub fn concat<'a>(parts: impl IntoIterator<Item = BitStream<'a>>) -> Self {
+        let mut bits = Self::new();
+        for part in parts {
+            bits.extend_from_stream(&part);
+        }
+        bits
+    }
+
+    /// Returns the number of bits in the buffer.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.bit_count
+    }
+
+    /// Returns true if the buffer holds no bits.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.bit_count == 0
+    }
+
+    /// Borrows the buffer as a [`BitStream`].
+    pub fn as_stream(&self) -> BitStream<'_> {
+        BitStream::new(&self.buffer, self.bit_count).expect("buffer holds every bit")
+    }
+
+    /// Returns the left-aligned bytes holding the bits.
+    ///
+    /// Any bits in the last byte past [`len`](BitBuf::len) are zeroed.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Returns the left-aligned bytes holding the bits, consuming the buffer.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buffer
+    }
+
+    /// Returns the bit at `index`, or `None` if it is out of range.
+    pub fn get(&self, index: usize) -> Option<bool> {
+        (index < self.bit_count).then(|| (self.buffer[index / 8] >> (7 - index % 8)) & 1 == 1)
+    }
+
+    /// Sets the bit at `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of range.
+    pub fn set(&mut self, index: usize, bit: bool) {
+        assert!(
+            index < self.bit_count,
+            "bit index {} out of range for {} bits",
+            index,
+            self.bit_count
+        );
+        let mask = 1 << (7 - index % 8);
+        if bit {
+            self.buffer[index / 8] |= mask;
+        } else {
+            self.buffer[index / 8] &= !mask;
+        }
+    }
+
+    /// Returns an iterator over the bits.
+    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        (0..self.bit_count).map(|index| self.get(index).expect("index is in range"))
+    }
+
+    /// Appends a bit.
+    pub fn push(&mut self, bit: bool) {
+        if self.bit_count % 8 == 0 {
+            self.buffer.push(0);
+        }
+        self.bit_count += 1;
+        self.set(self.bit_count - 1, bit);
+    }
+
+    /// Removes the last bit and returns it, or `None` if the buffer is empty.
+    pub fn pop(&mut self) -> Option<bool> {
+        let bit = self.get(self.bit_count.checked_sub(1)?)?;
+        self.truncate(self.bit_count - 1);
+        Some(bit)
+    }
+
+    /// Shortens the buffer to `len` bits, doing nothing if it is already
+    /// that short.
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.bit_count {
+            return;
+        }
+        self.buffer.truncate(len.div_ceil(8));
+        self.bit_count = len;
+        self.clear_unused_bits();
+    }
+
+    /// Inserts a bit at `index`, shifting the bits after it along.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than the length.
+    pub fn insert(&mut self, index: usize, bit: bool) {
+        assert!(
+            index <= self.bit_count,
+            "insertion index {} out of range for {} bits",
+            index,
+            self.bit_count
+        );
+        let rest = self.slice(index..self.bit_count);
+        self.truncate(index);
+        self.push(bit);
+        self.extend_from_stream(&rest.as_stream());
+    }
+
+    /// Removes the bit at `index` and returns it, shifting the bits after it
+    /// back.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of range.
+    pub fn remove(&mut self, index: usize) -> bool {
+        let bit = self.get(index).unwrap_or_else(|| {
+            panic!(
+                "removal index {} out of range for {} bits",
+                index, self.bit_count
+            )
+        });
+        let rest = self.slice(index + 1..self.bit_count);
+        self.truncate(index);
+        self.extend_from_stream(&rest.as_stream());
+        bit
+    }
+
+    /// Appends the bits of a stream.
+    pub fn extend_from_stream(&mut self, stream: &BitStream) {
+        self.extend((0..stream.len()).map(|index| stream.bit(index) == 1));
+    }
+
+    /// Returns a copy of the bits in `range`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` runs past the end of the buffer.
+    pub fn slice(&self, range: Range<usize>) -> BitBuf {
+        assert!(
+            range.start <= range.end && range.end <= self.bit_count,
+            "range {:?} out of range for {} bits",
+            range,
+            self.bit_count
+        );
+        BitBuf {
+            bit_count: range.len(),
+            buffer: self.as_stream().range_buffer(range),
+        }
+    }
+
+    /// Reverses the order of the bits, as for a card swiped backwards.
+    pub fn reverse(&mut self) {
+        self.buffer = self.as_stream().reversed_buffer();
+    }
+
+    /// Flips every bit, as for a reader that inverts the polarity.
+    pub fn invert(&mut self) {
+        for byte in &mut self.buffer {
+            *byte = !*byte;
+        }
+        self.clear_unused_bits();
+    }
+
+    /// Zeroes the bits of the final byte past the length
+    fn clear_unused_bits(&mut self) {
+        if self.bit_count % 8 != 0 {
+            *self.buffer.last_mut().expect("partial byte") &= 0xFF << (8 - self.bit_count % 8);
+        }
+    }
+}
+
+impl From<&BitStream<'_>> for BitBuf {
+    fn from(stream: &BitStream<'_>) -> Self {
+        Self::from_stream(stream)
+    }
+}
+
+impl From<BitStream<'_>> for BitBuf {
+    fn from(stream: BitStream<'_>) -> Self {
+        Self::from_stream(&stream)
+    }
+}
+
+impl Extend<bool> for BitBuf {
+    fn extend<I: IntoIterator<Item = bool>>(&mut self, bits: I) {
+        for bit in bits {
+            self.push(bit);
+        }
+    }
+}
+
+impl FromIterator<bool> for BitBuf {
+    fn from_iter<I: IntoIterator<Item = bool>>(bits: I) -> Self {
+        let mut buf = Self::new();
+        buf.extend(bits);
+        buf
+    }
+}
+
+impl fmt::Display for BitBuf {
+    /// Writes the bits as a string of `0` and `1` characters
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for bit in self.iter() {
+            f.write_str(if bit { "1" } else { "0" })?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for BitBuf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "BitBuf(")?;
+        for (index, bit) in self.iter().enumerate() {
+            if index > 0 && index % 8 == 0 {
+                write!(f, ":")?;
+            }
+            write!(f, "{}", u8::from(bit))?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl FromStr for BitBuf {
+    type Err = BitStreamError;
+
+    /// Parses a string of `0` and `1` characters
+    ///
+    /// Whitespace and `_` may separate the bits for readability. Any other
+    /// character is an [`BitStreamError::InvalidBit`] at its byte offset.
+    fn from_str(binary: &str) -> Result<Self, Self::Err> {
+        let mut bits = Self::with_capacity(binary.len());
+        for (offset, value) in binary.bytes().enumerate() {
+            match value {
+                b'0' => bits.push(false),
+                b'1' => bits.push(true),
+                b'_' => {}
+                value if value.is_ascii_whitespace() => {}
+                value => return Err(BitStreamError::InvalidBit { offset, value }),
+            }
+        }
+        Ok(bits)
+    }
+}
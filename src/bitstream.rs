@@ -1,12 +1,28 @@
 use std::fmt;
+use std::ops::Range;
+
+/// The shortest run of identical bits at either end of a stream that is taken
+/// to be clock bits rather than data.
+///
+/// No character starts with more than a few identical bits, while readers
+/// record dozens of clock bits either side of a swipe.
+const MIN_CLOCK_RUN: usize = 8;
+
+/// The bits of a clock run kept next to the data.
+///
+/// A character may end, or when swiped backwards begin, with bits that match
+/// the clock. The widest character has eight bits, at least one of which
+/// differs from the clock, so seven bits are enough to keep it whole.
+const CLOCK_MARGIN: usize = 7;
 
 /// An immutable bit stream that wraps a byte slice with a specific bit count.
 ///
 /// The internal buffer is left-aligned, meaning the significant bits start from
-/// the MSB of the first byte, and any trailing bits in the last byte are zeroed.
-/// The buffer is automatically shrunk to the minimum size needed to store the
-/// specified number of bits during construction.
-#[derive(Clone, PartialEq, Eq)]
+/// the MSB of the first byte. Any bits in the last byte past the bit count are
+/// not part of the stream and are ignored. The buffer is automatically shrunk
+/// to the minimum size needed to store the specified number of bits during
+/// construction.
+#[derive(Clone)]
 pub struct BitStream<'a> {
     buffer: &'a [u8],
     bit_count: usize,
@@ -20,6 +36,9 @@ pub enum BitStreamError {
         required_bytes: usize,
         provided_bytes: usize,
     },
+
+    /// A byte that should hold a single bit holds something else.
+    InvalidBit { offset: usize, value: u8 },
 }
 
 impl fmt::Display for BitStreamError {
@@ -35,6 +54,9 @@ impl fmt::Display for BitStreamError {
                     required_bytes, provided_bytes
                 )
             }
+            BitStreamError::InvalidBit { offset, value } => {
+                write!(f, "Invalid bit 0x{:02X} at offset {}", value, offset)
+            }
         }
     }
 }
@@ -45,8 +67,8 @@ impl<'a> BitStream<'a> {
     /// Creates a new BitStream from a byte slice and bit count.
     ///
     /// The buffer will be shrunk to the minimum size needed to hold the specified
-    /// number of bits. The last bits in the final byte must be zeroed if they
-    /// are not part of the bit stream.
+    /// number of bits. The last bits in the final byte need not be zeroed if
+    /// they are not part of the bit stream; whatever they hold is ignored.
     ///
     /// # Arguments
     ///
@@ -85,6 +107,48 @@ impl<'a> BitStream<'a> {
         })
     }
 
+    /// Creates a BitStream from a whole reader buffer whose bit count is not
+    /// known.
+    ///
+    /// Readers that do not report how many bits they captured leave junk in
+    /// the unused bits of the final byte. The stream ends where the longest
+    /// run of identical bits reaching into the final byte ends, so the
+    /// trailing clock bits are kept and anything after them is ignored.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// // Clock bits run into the final byte, followed by junk
+    /// let data = vec![0b11010110, 0b00000000, 0b00010111];
+    /// let stream = magstripe_rs::BitStream::from_raw(&data);
+    /// assert_eq!(stream.len(), 19);
+    /// assert_eq!(stream.data_window(), 0..14);
+    /// ```
+    pub fn from_raw(buffer: &'a [u8]) -> Self {
+        let whole = BitStream {
+            buffer,
+            bit_count: buffer.len() * 8,
+        };
+        if whole.is_empty() {
+            return whole;
+        }
+
+        // Consider every end in the final byte, preferring the longest run and
+        // then the least junk
+        let final_byte = (whole.bit_count - 1) / 8 * 8;
+        let mut end = whole.bit_count;
+        let mut longest = 0;
+        for candidate in final_byte.max(1)..=whole.bit_count {
+            let run = whole.run_before(candidate);
+            if run >= longest {
+                longest = run;
+                end = candidate;
+            }
+        }
+
+        BitStream::new(buffer, end).expect("end is within the buffer")
+    }
+
     /// Returns the number of bits in the stream.
     #[inline]
     pub fn len(&self) -> usize {
@@ -99,13 +163,128 @@ impl<'a> BitStream<'a> {
 
     /// Returns the internal byte buffer.
     ///
-    /// The buffer is left-aligned, with any trailing bits in the last byte zeroed.
+    /// The buffer is left-aligned. Any bits in the last byte past [`len`](BitStream::len)
+    /// are not part of the stream and may hold anything.
     #[inline]
     pub fn buffer(&self) -> &'a [u8] {
         self.buffer
     }
+
+    /// Finds the bits that hold data, between the clock runs at either end.
+    ///
+    /// A run of at least eight identical bits at the start or end of the
+    /// stream is taken to be clock bits, whether they are zeros or, from an
+    /// inverting reader, ones. Shorter runs are kept as data. The window is
+    /// empty if the stream holds nothing but clock bits.
+    ///
+    /// The last bits of a track may match the clock value, as in a check
+    /// character ending in zeros, and cannot be told apart from the run. The
+    /// seven bits of each run next to the data are therefore kept in the
+    /// window, so that it holds every character whole and still decodes.
+    pub fn data_window(&self) -> Range<usize> {
+        let leading = self.run_after(0);
+        if leading == self.bit_count {
+            return self.bit_count..self.bit_count;
+        }
+        let trailing = self.run_before(self.bit_count);
+
+        let start = if leading >= MIN_CLOCK_RUN {
+            leading - CLOCK_MARGIN
+        } else {
+            0
+        };
+        let end = if trailing >= MIN_CLOCK_RUN {
+            self.bit_count - trailing + CLOCK_MARGIN
+        } else {
+            self.bit_count
+        };
+        start..end
+    }
+
+    /// Returns a left-aligned buffer holding only the bits of the
+    /// [data window](BitStream::data_window), with the clock runs stripped.
+    ///
+    /// Wrapping the returned buffer in a new `BitStream` of
+    /// `data_window().len()` bits gives the normalised stream.
+    pub fn trimmed_buffer(&self) -> Vec<u8> {
+        self.range_buffer(self.data_window())
+    }
+
+    /// Returns the bit at `index`, which must be within the stream
+    pub(crate) fn bit(&self, index: usize) -> u8 {
+        (self.buffer[index / 8] >> (7 - index % 8)) & 1
+    }
+
+    /// Counts the identical bits starting at `start`
+    fn run_after(&self, start: usize) -> usize {
+        if start >= self.bit_count {
+            return 0;
+        }
+        let value = self.bit(start);
+        (start..self.bit_count)
+            .take_while(|&index| self.bit(index) == value)
+            .count()
+    }
+
+    /// Counts the identical bits ending just before `end`
+    fn run_before(&self, end: usize) -> usize {
+        if end == 0 {
+            return 0;
+        }
+        let value = self.bit(end - 1);
+        (0..end)
+            .rev()
+            .take_while(|&index| self.bit(index) == value)
+            .count()
+    }
+
+    /// Returns a left-aligned buffer holding the same bits in reverse order.
+    ///
+    /// A card swiped backwards produces the recorded bits end-to-end reversed;
+    /// wrapping the returned buffer in a new `BitStream` of the same length
+    /// restores the recorded order.
+    pub(crate) fn reversed_buffer(&self) -> Vec<u8> {
+        let mut reversed = vec![0u8; self.buffer.len()];
+
+        for bit_idx in 0..self.bit_count {
+            let bit = self.bit(self.bit_count - 1 - bit_idx);
+            reversed[bit_idx / 8] |= bit << (7 - bit_idx % 8);
+        }
+
+        reversed
+    }
+
+    /// Returns a left-aligned buffer holding the bits from `start` onwards.
+    ///
+    /// Wrapping the returned buffer in a new `BitStream` of `len() - start`
+    /// bits gives the tail of this stream.
+    pub(crate) fn tail_buffer(&self, start: usize) -> Vec<u8> {
+        self.range_buffer(start.min(self.bit_count)..self.bit_count)
+    }
+
+    /// Returns a left-aligned buffer holding the bits in `range`
+    pub(crate) fn range_buffer(&self, range: Range<usize>) -> Vec<u8> {
+        let mut slice = vec![0u8; range.len().div_ceil(8)];
+
+        for (bit_idx, source) in range.enumerate() {
+            slice[bit_idx / 8] |= self.bit(source) << (7 - bit_idx % 8);
+        }
+
+        slice
+    }
 }
 
+impl PartialEq for BitStream<'_> {
+    /// Streams are equal if they hold the same bits, whatever is left in the
+    /// unused bits of their final bytes
+    fn eq(&self, other: &Self) -> bool {
+        self.bit_count == other.bit_count
+            && (0..self.bit_count).all(|index| self.bit(index) == other.bit(index))
+    }
+}
+
+impl Eq for BitStream<'_> {}
+
 impl<'a> fmt::Debug for BitStream<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "BitStream(")?;
@@ -144,6 +323,7 @@ impl<'a> fmt::Debug for BitStream<'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{CheckStatus, DecodePolicy, Decoder, Format};
 
     #[test]
     fn test_new_valid() {
@@ -200,6 +380,115 @@ mod tests {
         assert_eq!(debug_str, "BitStream(11010110:10101111:1100)");
     }
 
+    #[test]
+    fn test_reversed_buffer() {
+        // Trailing bits beyond the bit count must not leak into the result
+        let data = vec![0b11010110, 0b10101111];
+        let stream = BitStream::new(&data, 12).unwrap();
+        let reversed = stream.reversed_buffer();
+        let reversed_stream = BitStream::new(&reversed, 12).unwrap();
+        assert_eq!(format!("{:?}", reversed_stream), "BitStream(01010110:1011)");
+
+        // Reversing twice gives back the original bits
+        let twice = reversed_stream.reversed_buffer();
+        assert_eq!(twice, vec![0b11010110, 0b10100000]);
+    }
+
+    #[test]
+    fn test_tail_buffer() {
+        let data = vec![0b11010110, 0b10101111];
+        let stream = BitStream::new(&data, 12).unwrap();
+        let tail = stream.tail_buffer(3);
+        let tail_stream = BitStream::new(&tail, 9).unwrap();
+        assert_eq!(format!("{:?}", tail_stream), "BitStream(10110101:0)");
+
+        // A tail starting at or past the end is empty
+        assert!(stream.tail_buffer(12).is_empty());
+        assert!(stream.tail_buffer(20).is_empty());
+    }
+
+    #[test]
+    fn test_junk_in_final_byte_is_ignored() {
+        let clean = vec![0b11010110, 0b10100000];
+        let junk = vec![0b11010110, 0b10101011];
+        assert_eq!(
+            BitStream::new(&clean, 12).unwrap(),
+            BitStream::new(&junk, 12).unwrap()
+        );
+        assert_ne!(
+            BitStream::new(&clean, 12).unwrap(),
+            BitStream::new(&junk, 13).unwrap()
+        );
+        assert_eq!(
+            BitStream::new(&junk, 12).unwrap().reversed_buffer(),
+            BitStream::new(&clean, 12).unwrap().reversed_buffer()
+        );
+    }
+
+    #[test]
+    fn test_from_raw() {
+        // Clock zeros into the final byte, then junk
+        let data = vec![0b00000000, 0b01101000, 0b00000000, 0b00101101];
+        let stream = BitStream::from_raw(&data);
+        assert_eq!(stream.len(), 26);
+        assert_eq!(stream.data_window(), 2..20);
+
+        // Inverted clock ones with junk zeros after them
+        let data = vec![0b11111111, 0b10010111, 0b11111111, 0b11100000];
+        assert_eq!(BitStream::from_raw(&data).len(), 27);
+
+        // A clean final byte is kept whole
+        let data = vec![0b00000000, 0b01101000, 0b00000000, 0b00000000];
+        assert_eq!(BitStream::from_raw(&data).len(), 32);
+
+        assert!(BitStream::from_raw(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_data_window() {
+        let data = vec![0b00000000, 0b01101000, 0b00000000];
+        let stream = BitStream::new(&data, 24).unwrap();
+        assert_eq!(stream.data_window(), 2..20);
+        let trimmed = stream.trimmed_buffer();
+        assert_eq!(
+            format!("{:?}", BitStream::new(&trimmed, 18).unwrap()),
+            "BitStream(00000001:10100000:00)"
+        );
+
+        // Short runs at the ends are data, not clock bits
+        let data = vec![0b11010000, 0b00000000, 0b00001011];
+        let stream = BitStream::new(&data, 24).unwrap();
+        assert_eq!(stream.data_window(), 0..24);
+
+        // Nothing but clock bits
+        let data = vec![0xFF, 0xFF];
+        let stream = BitStream::new(&data, 16).unwrap();
+        assert!(stream.data_window().is_empty());
+        assert!(stream.trimmed_buffer().is_empty());
+    }
+
+    #[test]
+    fn test_trimmed_buffer_decodes() {
+        // The LRC of this card ends in bits that match the clock
+        let data = vec![
+            255, 255, 255, 151, 222, 246, 253, 190, 141, 247, 7, 127, 255, 255, 255, 255, 192,
+        ];
+        let stream = BitStream::from_raw(&data);
+        let window = stream.data_window();
+        let trimmed = stream.trimmed_buffer();
+        let trimmed = BitStream::new(&trimmed, window.len()).unwrap();
+
+        let formats = [Format::Track2Inverted];
+        for stream in [&stream, &trimmed] {
+            let output = Decoder::new(&formats)
+                .with_policy(DecodePolicy::strict())
+                .decode(stream.clone())
+                .unwrap();
+            assert_eq!(output.data, "0004048712");
+            assert_eq!(output.quality.check, CheckStatus::Verified);
+        }
+    }
+
     #[test]
     fn test_empty_stream() {
         let data = vec![];
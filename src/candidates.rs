@@ -0,0 +1,103 @@
+use crate::{DecodeReport, DecoderOutput};
+use std::cmp::Reverse;
+
+/// A successful decode of a bitstream, scored against the other formats that
+/// also decoded it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate<'a> {
+    /// The decoded data and the format that produced it.
+    pub output: DecoderOutput<'a>,
+
+    /// How strongly the bitstream supports this reading; higher is better.
+    ///
+    /// Points are given for a verified check character (8), per-character
+    /// parity that every character passed (4), each sentinel found (2 each),
+    /// and clock bits that run cleanly up to the first character and away
+    /// from the last one (1 each).
+    pub score: u32,
+}
+
+impl<'a> Candidate<'a> {
+    /// Score a successful decode of a bitstream `stream_len` bits long.
+    pub(crate) fn new(output: DecoderOutput<'a>, stream_len: usize) -> Self {
+        let score = score(&output, stream_len);
+        Self { output, score }
+    }
+
+    /// Whether the reading was confirmed by its check characters, without
+    /// any tolerated errors.
+    pub fn is_clean(&self) -> bool {
+        self.output.metadata.integrity_checked && self.output.errors.is_empty()
+    }
+}
+
+/// Every format that decoded a bitstream, best first.
+///
+/// Returned by [`crate::Decoder::decode_all`]. Candidates are ordered by
+/// score; candidates with equal scores keep the order they were attempted in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodeCandidates<'a> {
+    /// The successful decodes, ordered by descending score. Never empty.
+    pub candidates: Vec<Candidate<'a>>,
+
+    /// Whether two clean candidates decoded to different payloads.
+    ///
+    /// An ambiguous read should not be trusted without further checks.
+    pub ambiguous: bool,
+
+    /// Why each of the other attempts failed.
+    pub report: DecodeReport,
+}
+
+impl<'a> DecodeCandidates<'a> {
+    /// Rank the candidates and check them for ambiguity.
+    pub(crate) fn new(mut candidates: Vec<Candidate<'a>>, report: DecodeReport) -> Self {
+        candidates.sort_by_key(|candidate| Reverse(candidate.score));
+
+        let mut clean = candidates.iter().filter(|candidate| candidate.is_clean());
+        let ambiguous = match clean.next() {
+            Some(first) => clean.any(|other| other.output.bytes != first.output.bytes),
+            None => false,
+        };
+
+        Self {
+            candidates,
+            ambiguous,
+            report,
+        }
+    }
+
+    /// The highest scoring candidate.
+    pub fn best(&self) -> &Candidate<'a> {
+        &self.candidates[0]
+    }
+}
+
+/// How strongly a bitstream `stream_len` bits long supports a decode, see
+/// [`Candidate::score`]
+pub(crate) fn score(output: &DecoderOutput, stream_len: usize) -> u32 {
+    let metadata = &output.metadata;
+
+    let mut score = 0;
+    if metadata.integrity_checked {
+        score += 8;
+    }
+    if metadata.parity_checked && output.quality.parity_failures == 0 {
+        score += 4;
+    }
+    if metadata.start_sentinel_offset.is_some() {
+        score += 2;
+    }
+    if metadata.end_sentinel_offset.is_some() {
+        score += 2;
+    }
+    let start = metadata.bit_range.start;
+    if start > 0 && metadata.leading_clock_bits == start {
+        score += 1;
+    }
+    let end = metadata.bit_range.end;
+    if end < stream_len && metadata.trailing_clock_bits == stream_len - end {
+        score += 1;
+    }
+    score
+}
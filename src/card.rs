@@ -0,0 +1,263 @@
+use crate::candidates::score;
+use crate::{
+    BitStream, DecodePolicy, Decoder, DecoderError, Encoding, Format, OwnedDecoderOutput, Track,
+};
+use tracing::debug;
+
+/// The three tracks, in the order of the heads on a reader
+const TRACKS: [Track; 3] = [Track::Track1, Track::Track2, Track::Track3];
+
+/// A decoder for every track of a card at once.
+///
+/// Three-head readers deliver a bitstream per track. Each stream is decoded
+/// with the formats of its own track: the 7-bit alphanumeric Track 1
+/// formats, or the 5-bit numeric Track 2 and Track 3 formats. A track that
+/// fails to decode is reported in the [`Card`] without affecting the others.
+///
+/// # Example
+///
+/// ```
+/// use magstripe_rs::{BitStream, CardDecoder, Track};
+///
+/// let data = vec![
+///     255, 255, 255, 151, 222, 246, 253, 190, 141, 247, 7, 127, 255, 255, 255, 255, 192,
+/// ];
+/// let blank = vec![0u8; 16];
+/// let streams = [BitStream::new(&blank, 128).unwrap(), BitStream::new(&data, 130).unwrap()];
+///
+/// let card = CardDecoder::new().decode_unlabelled(&streams);
+/// assert_eq!(card.assignments, [Some(Track::Track1), Some(Track::Track2)]);
+/// assert_eq!(card.track2.unwrap().unwrap().data, "0004048712");
+/// assert!(card.track1.unwrap().is_err());
+/// assert!(card.track3.is_none());
+/// ```
+#[derive(Debug, Clone)]
+pub struct CardDecoder {
+    /// The formats tried for each track, indexed as [`TRACKS`]
+    formats: [Vec<Format>; 3],
+    try_reverse: bool,
+    policy: DecodePolicy,
+}
+
+/// The tracks decoded from a card.
+///
+/// Each track is `None` if no stream was read as that track, or the result
+/// of decoding the stream that was.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Card {
+    /// The result of decoding Track 1.
+    pub track1: Option<Result<OwnedDecoderOutput, DecoderError>>,
+
+    /// The result of decoding Track 2.
+    pub track2: Option<Result<OwnedDecoderOutput, DecoderError>>,
+
+    /// The result of decoding Track 3.
+    pub track3: Option<Result<OwnedDecoderOutput, DecoderError>>,
+
+    /// The track each stream was read as, in the order the streams were
+    /// given; `None` for streams beyond the third.
+    pub assignments: Vec<Option<Track>>,
+}
+
+impl Card {
+    /// The result of decoding a track, if a stream was read as that track.
+    pub fn track(&self, track: Track) -> Option<&Result<OwnedDecoderOutput, DecoderError>> {
+        match track {
+            Track::Track1 => self.track1.as_ref(),
+            Track::Track2 => self.track2.as_ref(),
+            Track::Track3 => self.track3.as_ref(),
+        }
+    }
+
+    /// The tracks that failed to decode and why.
+    pub fn errors(&self) -> impl Iterator<Item = (Track, &DecoderError)> {
+        TRACKS
+            .into_iter()
+            .filter_map(|track| Some((track, self.track(track)?.as_ref().err()?)))
+    }
+}
+
+impl Default for CardDecoder {
+    fn default() -> Self {
+        let inverted = Encoding::default().with_inverted(true);
+        Self {
+            formats: [
+                vec![Format::Track1, Format::Track1Inverted],
+                vec![Format::Track2, Format::Track2Inverted],
+                vec![Format::Track3, Format::Encoded(Track::Track3, inverted)],
+            ],
+            try_reverse: true,
+            policy: DecodePolicy::default(),
+        }
+    }
+}
+
+impl CardDecoder {
+    /// Create a card decoder trying each track as specified and inverted
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the formats tried for a track
+    pub fn with_track_formats(mut self, track: Track, formats: impl Into<Vec<Format>>) -> Self {
+        self.formats[track_index(track)] = formats.into();
+        self
+    }
+
+    /// Enable or disable decoding of backwards swipes, see
+    /// [`Decoder::with_reverse`]
+    pub fn with_reverse(mut self, try_reverse: bool) -> Self {
+        self.try_reverse = try_reverse;
+        self
+    }
+
+    /// Set how strictly bitstreams are held to their format
+    pub fn with_policy(mut self, policy: DecodePolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Decode streams whose tracks are known
+    pub fn decode_tracks(
+        &self,
+        track1: Option<BitStream>,
+        track2: Option<BitStream>,
+        track3: Option<BitStream>,
+    ) -> Card {
+        let mut card = Card {
+            track1: None,
+            track2: None,
+            track3: None,
+            assignments: Vec::new(),
+        };
+        for (track, stream) in TRACKS.into_iter().zip([track1, track2, track3]) {
+            if let Some(stream) = stream {
+                card.assignments.push(Some(track));
+                *slot(&mut card, track) = Some(self.decode_track(track, stream));
+            }
+        }
+        card
+    }
+
+    /// Decode up to three streams whose tracks are not known
+    ///
+    /// Every stream is decoded as every track, and the streams are matched
+    /// to the tracks so that the strongest decodes win, as scored by
+    /// [`Candidate::score`](crate::Candidate::score). A stream longer than a track allows is never
+    /// read as that track, which tells a long Track 3 from Track 2. When the
+    /// scores do not decide, streams are taken in head order, so a stream
+    /// that decodes as nothing reports the error of the track its position
+    /// suggests.
+    pub fn decode_unlabelled(&self, streams: &[BitStream]) -> Card {
+        // Decode every stream as every track
+        let decoded: Vec<Vec<(Result<OwnedDecoderOutput, DecoderError>, u32)>> = streams
+            .iter()
+            .take(TRACKS.len())
+            .map(|stream| {
+                TRACKS
+                    .into_iter()
+                    .map(|track| self.identify(track, stream.clone()))
+                    .collect()
+            })
+            .collect();
+
+        // Pick the assignment with the highest total score, the first in
+        // head order on ties
+        let mut best: Option<(u32, Vec<usize>)> = None;
+        for assignment in assignments(decoded.len(), TRACKS.len()) {
+            let total = assignment
+                .iter()
+                .enumerate()
+                .map(|(stream, &track)| decoded[stream][track].1)
+                .sum();
+            if best
+                .as_ref()
+                .map_or(true, |(best_total, _)| total > *best_total)
+            {
+                best = Some((total, assignment));
+            }
+        }
+        let assignment = best.map(|(_, assignment)| assignment).unwrap_or_default();
+        debug!("Assigned streams to tracks {:?}", assignment);
+
+        let mut card = Card {
+            track1: None,
+            track2: None,
+            track3: None,
+            assignments: vec![None; streams.len()],
+        };
+        for ((stream, row), track) in decoded.into_iter().enumerate().zip(assignment) {
+            card.assignments[stream] = Some(TRACKS[track]);
+            *slot(&mut card, TRACKS[track]) = row.into_iter().nth(track).map(|(result, _)| result);
+        }
+        card
+    }
+
+    /// Decode a stream with the formats of a track
+    fn decode_track(
+        &self,
+        track: Track,
+        stream: BitStream,
+    ) -> Result<OwnedDecoderOutput, DecoderError> {
+        self.identify(track, stream).0
+    }
+
+    /// Decode a stream with the formats of a track, scoring how strongly the
+    /// decode identifies the stream as that track; zero if it does not
+    fn identify(
+        &self,
+        track: Track,
+        stream: BitStream,
+    ) -> (Result<OwnedDecoderOutput, DecoderError>, u32) {
+        let stream_len = stream.len();
+        let result = Decoder::new(&self.formats[track_index(track)])
+            .with_reverse(self.try_reverse)
+            .with_policy(self.policy)
+            .decode(stream);
+        match result {
+            Ok(output) if output.metadata.characters.len() > track.max_length() => {
+                (Ok(output.into_owned()), 0)
+            }
+            Ok(output) => {
+                let score = score(&output, stream_len) + 1;
+                (Ok(output.into_owned()), score)
+            }
+            Err(error) => (Err(error), 0),
+        }
+    }
+}
+
+/// Every way of giving `streams` streams distinct tracks out of `tracks`, in
+/// lexicographic order
+fn assignments(streams: usize, tracks: usize) -> Vec<Vec<usize>> {
+    if streams == 0 {
+        return vec![Vec::new()];
+    }
+    let mut all = Vec::new();
+    for prefix in assignments(streams - 1, tracks) {
+        for track in (0..tracks).filter(|track| !prefix.contains(track)) {
+            let mut next = prefix.clone();
+            next.push(track);
+            all.push(next);
+        }
+    }
+    all
+}
+
+/// Position of a track in [`TRACKS`]
+fn track_index(track: Track) -> usize {
+    match track {
+        Track::Track1 => 0,
+        Track::Track2 => 1,
+        Track::Track3 => 2,
+    }
+}
+
+/// The result of a track in a card
+fn slot(card: &mut Card, track: Track) -> &mut Option<Result<OwnedDecoderOutput, DecoderError>> {
+    match track {
+        Track::Track1 => &mut card.track1,
+        Track::Track2 => &mut card.track2,
+        Track::Track3 => &mut card.track3,
+    }
+}
@@ -1,23 +1,56 @@
-mod common;
-mod custom;
+pub mod common;
+pub(crate) mod spec;
 mod track1;
 mod track2;
 mod track3;
+mod track_decoder;
 
-use crate::{BitStream, DecoderError, DecoderOutput, Format};
+use crate::{
+    BitStream, Candidate, DecodeCandidates, DecodePolicy, DecodeReport, DecoderError,
+    DecoderOutput, Encoding, Format, FormatAttempt, SwipeDirection, Track,
+};
 use tracing::{debug, trace, warn};
 
-pub use track2::decode_track2;
+pub use spec::{decode_spec, decode_spec_with_policy};
+pub use track1::{decode_track1, decode_track1_with};
+pub use track2::{decode_track2, decode_track2_with};
+pub use track3::{decode_track3, decode_track3_with};
+pub use track_decoder::{DecodedTrack, TrackDecoder};
 
 /// Main decode implementation that tries each format
+///
+/// Each format is tried on the bitstream as received, then on the reversed
+/// bitstream to pick up cards swiped backwards.
 pub fn decode_with_formats<'a>(
     formats: &'a [Format],
     stream: BitStream,
 ) -> Result<DecoderOutput<'a>, DecoderError> {
+    decode_with_options(formats, stream, true, &DecodePolicy::default())
+}
+
+/// Decode implementation shared by [`decode_with_formats`] and [`crate::Decoder`]
+pub(crate) fn decode_with_options<'a>(
+    formats: &'a [Format],
+    stream: BitStream,
+    try_reverse: bool,
+    policy: &DecodePolicy,
+) -> Result<DecoderOutput<'a>, DecoderError> {
+    decode_with_report(formats, stream, try_reverse, policy).0
+}
+
+/// Decode a bitstream, recording why each attempted format failed
+pub(crate) fn decode_with_report<'a>(
+    formats: &'a [Format],
+    stream: BitStream,
+    try_reverse: bool,
+    policy: &DecodePolicy,
+) -> (Result<DecoderOutput<'a>, DecoderError>, DecodeReport) {
+    let mut report = DecodeReport::default();
+
     // Check if any formats were provided
     if formats.is_empty() {
         warn!("No formats provided for decoding");
-        return Err(DecoderError::NoFormatsProvided);
+        return (Err(DecoderError::NoFormatsProvided), report);
     }
 
     debug!(
@@ -27,47 +60,152 @@ pub fn decode_with_formats<'a>(
     );
     trace!("Bitstream: {:?}", stream);
 
-    // Try each format in order
-    for format in formats {
-        debug!("Trying format: {:?}", format);
-        match try_decode_format(format, &stream) {
-            Ok(data) => {
-                debug!("Successfully decoded with {:?}: {}", format, data);
-                return Ok(DecoderOutput { data, format });
-            }
-            Err(e) => {
-                trace!("Format {:?} failed: {:?}", format, e);
-                // Continue to next format
-                continue;
-            }
+    // Try each format in order, forwards first so that a backwards reading
+    // never shadows a format that decodes the stream as received
+    if let Some(output) = try_formats(
+        formats,
+        &stream,
+        SwipeDirection::Forward,
+        policy,
+        &mut report,
+    ) {
+        return (Ok(output), report);
+    }
+
+    if try_reverse {
+        let reversed_buffer = stream.reversed_buffer();
+        let reversed = BitStream::new(&reversed_buffer, stream.len())
+            .expect("reversed buffer has the same length as the original");
+        trace!("Reversed bitstream: {:?}", reversed);
+
+        if let Some(output) = try_formats(
+            formats,
+            &reversed,
+            SwipeDirection::Reverse,
+            policy,
+            &mut report,
+        ) {
+            return (Ok(output), report);
         }
     }
 
     // None of the formats worked
     warn!("Failed to decode with any of {} formats", formats.len());
-    Err(DecoderError::NoValidFormat {
+    let error = DecoderError::NoValidFormat {
         attempted: formats.len(),
-    })
+        report: report.clone(),
+    };
+    (Err(error), report)
+}
+
+/// Try each format in order on a stream read in the given direction,
+/// recording each failure in the report
+fn try_formats<'a>(
+    formats: &'a [Format],
+    stream: &BitStream,
+    direction: SwipeDirection,
+    policy: &DecodePolicy,
+    report: &mut DecodeReport,
+) -> Option<DecoderOutput<'a>> {
+    formats
+        .iter()
+        .find_map(|format| attempt_format(format, stream, direction, policy, report))
 }
 
-/// Try to decode with a specific format
-fn try_decode_format(format: &Format, stream: &BitStream) -> Result<String, DecoderError> {
-    match format {
-        Format::Track2 => track2::decode_track2(stream, false, true, false, false, false),
-        Format::Track2Inverted => track2::decode_track2(stream, true, true, false, false, false),
-        Format::Track2MSB => track2::decode_track2(stream, false, false, false, false, false),
-        Format::Track2LSB => track2::decode_track2(stream, false, true, false, false, false),
-        Format::Track2Raw => track2::decode_track2(stream, false, true, true, false, false),
-        Format::Track2SwappedParity => {
-            track2::decode_track2(stream, false, true, false, true, false)
+/// Try a single format on a stream read in the given direction, recording a
+/// failure in the report
+fn attempt_format<'a>(
+    format: &'a Format,
+    stream: &BitStream,
+    direction: SwipeDirection,
+    policy: &DecodePolicy,
+    report: &mut DecodeReport,
+) -> Option<DecoderOutput<'a>> {
+    debug!("Trying format: {:?} ({:?})", format, direction);
+    match format.decode(stream, policy) {
+        Ok(DecodedTrack {
+            data,
+            bytes,
+            metadata,
+            quality,
+            errors,
+        }) => {
+            debug!("Successfully decoded with {:?}: {}", format, data);
+            Some(DecoderOutput {
+                data,
+                bytes,
+                format,
+                direction,
+                metadata,
+                quality,
+                errors,
+            })
         }
-        Format::Track2EvenParity => track2::decode_track2(stream, false, true, false, false, true),
+        Err(error) => {
+            trace!("Format {:?} failed: {:?}", format, error);
+            report.attempts.push(FormatAttempt {
+                format: format.clone(),
+                direction,
+                error,
+            });
+            None
+        }
+    }
+}
+
+/// Decode a bitstream with every format, in both directions if enabled, and
+/// rank the formats that succeed
+pub(crate) fn decode_all<'a>(
+    formats: &'a [Format],
+    stream: BitStream,
+    try_reverse: bool,
+    policy: &DecodePolicy,
+) -> Result<DecodeCandidates<'a>, DecoderError> {
+    if formats.is_empty() {
+        warn!("No formats provided for decoding");
+        return Err(DecoderError::NoFormatsProvided);
+    }
 
-        Format::Track1 => track1::decode_track1(stream, false),
-        Format::Track1Inverted => track1::decode_track1(stream, true),
+    let mut report = DecodeReport::default();
+    let mut candidates = Vec::new();
 
-        Format::Track3 => track3::decode_track3(stream),
+    let mut attempt_all = |oriented: &BitStream, direction: SwipeDirection| {
+        for format in formats {
+            if let Some(output) = attempt_format(format, oriented, direction, policy, &mut report) {
+                candidates.push(Candidate::new(output, stream.len()));
+            }
+        }
+    };
+
+    attempt_all(&stream, SwipeDirection::Forward);
+    if try_reverse {
+        let reversed_buffer = stream.reversed_buffer();
+        let reversed = BitStream::new(&reversed_buffer, stream.len())
+            .expect("reversed buffer has the same length as the original");
+        attempt_all(&reversed, SwipeDirection::Reverse);
+    }
+
+    if candidates.is_empty() {
+        warn!("Failed to decode with any of {} formats", formats.len());
+        return Err(DecoderError::NoValidFormat {
+            attempted: formats.len(),
+            report,
+        });
+    }
+
+    debug!("{} of the attempts decoded successfully", candidates.len());
+    Ok(DecodeCandidates::new(candidates, report))
+}
 
-        Format::Custom(spec) => custom::decode_custom(stream, spec),
+/// Decode an ISO track with the given encoding options
+pub fn decode_track(
+    stream: &BitStream,
+    track: Track,
+    encoding: &Encoding,
+) -> Result<String, DecoderError> {
+    match track {
+        Track::Track1 => track1::decode_track1_with(stream, encoding),
+        Track::Track2 => track2::decode_track2_with(stream, encoding),
+        Track::Track3 => track3::decode_track3_with(stream, encoding),
     }
 }
@@ -1,4 +1,8 @@
-use crate::{BitStream, ParityType};
+//! Building blocks for reading and checking characters, used by the
+//! built-in formats and available to [`TrackDecoder`](super::TrackDecoder)
+//! implementations.
+
+use crate::{BitStream, CrcSpec, ParityType};
 
 /// Extract a single character's worth of bits from the stream
 pub fn extract_bits(stream: &BitStream, offset: usize, bits_per_char: u8) -> Option<u8> {
@@ -50,6 +54,20 @@ pub fn extract_bits_msb(stream: &BitStream, offset: usize, bits_per_char: u8) ->
     Some(result)
 }
 
+/// Find the first bit offset at which `read_char` yields the start sentinel
+///
+/// Every bit offset is tested, so the run of leading clock bits does not need
+/// to be a multiple of the character width.
+pub fn find_start_sentinel(
+    stream: &BitStream,
+    bits_per_char: u8,
+    sentinel: u8,
+    read_char: impl Fn(usize) -> Option<u8>,
+) -> Option<usize> {
+    let last_offset = stream.len().checked_sub(bits_per_char as usize)?;
+    (0..=last_offset).find(|&offset| read_char(offset) == Some(sentinel))
+}
+
 /// Invert all bits in a byte
 pub fn invert_bits(byte: u8) -> u8 {
     !byte
@@ -80,53 +98,101 @@ pub fn check_parity(value: u8, bits: u8, parity_type: &ParityType) -> bool {
     }
 }
 
-/// Calculate LRC (Longitudinal Redundancy Check) for Track 2
-pub fn calculate_lrc_track2(data: &[u8]) -> u8 {
-    let mut lrc = 0u8;
-
-    for &byte in data {
-        // XOR all bytes together
-        lrc ^= byte;
+/// Read a character from the stream in normalised form
+///
+/// The returned character is normalised so that the first data bit of an
+/// LSB-first character is bit 0, and any inversion has been undone.
+pub fn read_char(
+    stream: &BitStream,
+    offset: usize,
+    bits_per_char: u8,
+    lsb_first: bool,
+    inverted: bool,
+) -> Option<u8> {
+    let char_bits = if lsb_first {
+        extract_bits(stream, offset, bits_per_char)?
+    } else {
+        extract_bits_msb(stream, offset, bits_per_char)?
+    };
+
+    if inverted {
+        Some(invert_bits(char_bits) & char_mask(bits_per_char))
+    } else {
+        Some(char_bits)
     }
+}
 
-    // For Track 2, LRC uses even parity (opposite of character parity)
-    // Count the bits and adjust if needed
-    let mut count = 0;
-    for i in 0..5 {
-        if (lrc >> i) & 1 == 1 {
-            count += 1;
-        }
-    }
+/// Mask covering the low `bits_per_char` bits of a byte
+pub fn char_mask(bits_per_char: u8) -> u8 {
+    ((1u16 << bits_per_char) - 1) as u8
+}
 
-    // If odd number of bits, flip the parity bit to make it even
-    if count % 2 == 1 {
-        lrc ^= 0b10000; // Flip the 5th bit (parity bit)
-    }
+/// Remove the parity bit at `parity_position`, closing the gap it leaves
+pub fn strip_parity(char_bits: u8, parity_position: u8) -> u8 {
+    let low = char_bits & char_mask(parity_position);
+    let high = ((u16::from(char_bits) >> (parity_position + 1)) << parity_position) as u8;
+    low | high
+}
 
-    lrc
+/// Insert a parity bit at `parity_position`, the inverse of [`strip_parity`]
+pub fn insert_parity(data_bits: u8, parity_position: u8, parity_type: &ParityType) -> u8 {
+    let low = data_bits & char_mask(parity_position);
+    let high = (u16::from(data_bits) << 1) as u8 & !char_mask(parity_position + 1);
+    let ones = data_bits.count_ones();
+    let parity_bit = match parity_type {
+        ParityType::Odd => u8::from(ones % 2 == 0),
+        ParityType::Even => u8::from(ones % 2 == 1),
+        ParityType::None => 0,
+    };
+    low | high | (parity_bit << parity_position)
 }
 
-/// Calculate LRC for Track 1
-pub fn calculate_lrc_track1(data: &[u8]) -> u8 {
-    let mut lrc = 0u8;
+/// Calculate the LRC (Longitudinal Redundancy Check) over data bits
+///
+/// Each bit of the LRC is the even parity of that bit across every character,
+/// so the result is the XOR of all characters' data bits. The LRC character
+/// carries its own parity bit, which is checked like any other character.
+pub fn calculate_lrc(data: &[u8]) -> u8 {
+    data.iter().fold(0, |lrc, &byte| lrc ^ byte)
+}
 
-    for &byte in data {
-        // XOR all bytes together (excluding parity bit)
-        lrc ^= byte & 0x3F; // Only use the 6 data bits
-    }
+/// Count the clock bits (zeros, or ones when inverted) at the given bit
+/// positions, stopping at the first data bit
+pub fn count_clock_bits(
+    stream: &BitStream,
+    positions: impl Iterator<Item = usize>,
+    inverted: bool,
+) -> usize {
+    positions
+        .take_while(|&position| extract_bits(stream, position, 1) == Some(u8::from(inverted)))
+        .count()
+}
+
+/// Calculate a CRC over bytes, bit by bit, in the Rocksoft model
+pub fn calculate_crc(spec: &CrcSpec, data: &[u8]) -> u32 {
+    let width = u32::from(spec.width);
+    let mask = u64::MAX >> (64 - width);
+    let top = 1u64 << (width - 1);
+    let mut register = u64::from(spec.init) & mask;
 
-    // Add even parity to the 6 data bits
-    let mut count = 0;
-    for i in 0..6 {
-        if (lrc >> i) & 1 == 1 {
-            count += 1;
+    for &byte in data {
+        let byte = if spec.reflect {
+            byte.reverse_bits()
+        } else {
+            byte
+        };
+        for bit_idx in (0..8).rev() {
+            let feedback = (register & top != 0) != ((byte >> bit_idx) & 1 == 1);
+            register = (register << 1) & mask;
+            if feedback {
+                register ^= u64::from(spec.poly);
+            }
         }
     }
 
-    // Set the 7th bit for odd parity of the first 6 bits
-    if count % 2 == 0 {
-        lrc |= 0x40; // Set bit 6
+    if spec.reflect {
+        register = register.reverse_bits() >> (64 - width);
     }
 
-    lrc
+    (register as u32 ^ spec.xor_out) & mask as u32
 }
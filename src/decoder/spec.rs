@@ -0,0 +1,481 @@
+use super::common::{
+    calculate_crc, calculate_lrc, check_parity, count_clock_bits, find_start_sentinel,
+    insert_parity, read_char, strip_parity,
+};
+use super::DecodedTrack;
+use crate::{
+    Alphabet, BitStream, CharacterInfo, CharacterKind, DecodeMetadata, DecodePolicy, DecoderError,
+    FormatSpec, Integrity, LrcSpec, SwipeQuality,
+};
+use tracing::{debug, trace};
+
+/// Decode a bitstream according to a format specification
+///
+/// Every format, built-in or custom, is decoded here. The start sentinel is
+/// searched for at every bit offset, characters are checked for parity, and
+/// the integrity check declared by the spec is verified after the end sentinel.
+pub fn decode_spec(stream: &BitStream, spec: &FormatSpec) -> Result<String, DecoderError> {
+    decode_spec_with_policy(stream, spec, &DecodePolicy::default())
+}
+
+/// Decode a bitstream according to a format specification and decode policy
+pub fn decode_spec_with_policy(
+    stream: &BitStream,
+    spec: &FormatSpec,
+    policy: &DecodePolicy,
+) -> Result<String, DecoderError> {
+    decode_spec_payload(stream, spec, policy).map(|decoded| decoded.data)
+}
+
+/// Decode a bitstream according to a format specification, keeping both the
+/// text and the bytes of the payload
+pub(crate) fn decode_spec_payload(
+    stream: &BitStream,
+    spec: &FormatSpec,
+    policy: &DecodePolicy,
+) -> Result<DecodedTrack, DecoderError> {
+    decode_spec_progress(stream, spec, policy, &mut Progress::default())
+}
+
+/// What the engine had read when it stopped
+///
+/// On success both fields are moved into the [`DecodedTrack`]; on failure
+/// they hold the characters read and the errors tolerated before the error.
+#[derive(Debug, Default)]
+pub(crate) struct Progress {
+    pub(crate) metadata: DecodeMetadata,
+    pub(crate) errors: Vec<DecoderError>,
+}
+
+/// Decode a bitstream according to a format specification, recording how far
+/// the decode got in `progress`
+pub(crate) fn decode_spec_progress(
+    stream: &BitStream,
+    spec: &FormatSpec,
+    policy: &DecodePolicy,
+    progress: &mut Progress,
+) -> Result<DecodedTrack, DecoderError> {
+    spec.validate()?;
+    debug!("Decoding with {:?}", spec);
+
+    let bits_per_char = spec.bits_per_char as usize;
+    let parity_bit = spec.parity_bit();
+
+    // Check minimum length (the sentinels plus at least one character)
+    let framing_chars =
+        usize::from(spec.start_sentinel.is_some()) + usize::from(spec.end_sentinel.is_some());
+    let minimum_required = (framing_chars + 1) * bits_per_char;
+    if stream.len() < minimum_required {
+        return Err(DecoderError::BitstreamTooShort {
+            bit_count: stream.len(),
+            minimum_required,
+        });
+    }
+
+    // Read a character, undoing the wire bit order and any inversion
+    let read = |offset: usize| {
+        read_char(
+            stream,
+            offset,
+            spec.bits_per_char,
+            spec.lsb_first,
+            spec.inverted,
+        )
+    };
+
+    // Split a character into its data bits, dropping the parity bit if any
+    let data_of = |char_bits: u8| match parity_bit {
+        Some(position) => strip_parity(char_bits, position),
+        None => char_bits,
+    };
+
+    let mut decoded = Vec::new();
+    let mut offset = 0;
+    let mut chars_read = Vec::new();
+    let Progress { metadata, errors } = progress;
+
+    // First, search for start sentinel with single-bit alignment if needed
+    if let Some(start_sentinel) = spec.start_sentinel {
+        let start_offset = find_start(stream, spec).ok_or(DecoderError::InvalidStartSentinel)?;
+        debug!("Found start sentinel at bit offset {}", start_offset);
+
+        chars_read.push(data_of(start_sentinel));
+        metadata.start_sentinel_offset = Some(start_offset);
+        metadata.characters.push(CharacterInfo {
+            offset: start_offset,
+            code: start_sentinel,
+            kind: CharacterKind::StartSentinel,
+        });
+        offset = start_offset + bits_per_char;
+    }
+    let first_offset = metadata.start_sentinel_offset.unwrap_or(0);
+
+    let end_sentinel = spec.end_sentinel.map(data_of);
+    let mut found_end = false;
+
+    // Errors tolerated in place of bad characters, see `DecodePolicy::placeholder`
+    let placeholder = policy.placeholder.unwrap_or(char::REPLACEMENT_CHARACTER);
+    let tolerate = |errors: &mut Vec<DecoderError>, error: DecoderError| match policy.placeholder {
+        Some(_) => {
+            debug!("Tolerating error: {}", error);
+            errors.push(error);
+            Ok(())
+        }
+        None => Err(error),
+    };
+
+    // A single character failing parity can be rebuilt from the LRC
+    let correcting = policy.correct_errors && matches!(spec.integrity, Integrity::Lrc(_));
+    let mut suspect: Option<Suspect> = None;
+    let mut can_correct = correcting;
+
+    // Process the stream - now that we found the start sentinel
+    while offset + bits_per_char <= stream.len() {
+        // Extract character bits
+        let char_bits = read(offset).ok_or(DecoderError::BitstreamTooShort {
+            bit_count: stream.len(),
+            minimum_required: offset + bits_per_char,
+        })?;
+        trace!("Character bits at offset {}: {:b}", offset, char_bits);
+
+        // Without an end sentinel, a track that runs into clock bits has ended
+        if policy.allow_missing_end_sentinel
+            && end_sentinel.is_some()
+            && count_clock_bits(stream, offset..stream.len(), spec.inverted)
+                == stream.len() - offset
+        {
+            debug!(
+                "Track ends without an end sentinel at bit offset {}",
+                offset
+            );
+            break;
+        }
+
+        // Store the data bits for the integrity check
+        let data_bits = data_of(char_bits);
+        let position = chars_read.len();
+        chars_read.push(data_bits);
+
+        // A bad character is either fatal or replaced by the placeholder,
+        // unless it is the only one and may be rebuilt later
+        let parity_ok = check_parity(char_bits, spec.bits_per_char, &spec.parity);
+        if !parity_ok {
+            if can_correct {
+                debug!("Character {} failed parity, trying to rebuild it", position);
+                can_correct = false;
+                suspect = Some(Suspect {
+                    position,
+                    decoded_index: decoded.len(),
+                    metadata_index: metadata.characters.len(),
+                });
+            } else {
+                if let Some(earlier) = suspect.take() {
+                    tolerate(errors, earlier.error())?;
+                }
+                tolerate(errors, DecoderError::ParityError { position })?;
+            }
+        }
+
+        // Check for end sentinel
+        if parity_ok && Some(data_bits) == end_sentinel {
+            debug!("Found end sentinel at bit offset {}", offset);
+            found_end = true;
+            metadata.end_sentinel_offset = Some(offset);
+            metadata.characters.push(CharacterInfo {
+                offset,
+                code: char_bits,
+                kind: CharacterKind::EndSentinel,
+            });
+            offset += bits_per_char;
+
+            // Read the check characters, which may be cut off by the end of the stream
+            let check_count = spec.integrity.check_chars(spec.data_bits_per_char());
+            let check_chars: Option<Vec<u8>> = (0..check_count)
+                .map(|i| read(offset + i * bits_per_char))
+                .collect();
+            if check_chars.is_none() && policy.require_check {
+                return Err(DecoderError::MissingCheckCharacter);
+            }
+
+            // Rebuild a damaged character, or the LRC itself, from the 2-D parity
+            let lrc_bits = check_chars
+                .as_ref()
+                .and_then(|chars| chars.first().copied());
+            let mut lrc_corrected = false;
+            if let (true, Integrity::Lrc(lrc), Some(lrc_bits)) =
+                (correcting, spec.integrity, lrc_bits)
+            {
+                let lrc_parity_ok = check_parity(lrc_bits, spec.bits_per_char, &lrc.parity);
+                match suspect {
+                    Some(damaged) if lrc_parity_ok => {
+                        chars_read[damaged.position] = 0;
+                        let rebuilt =
+                            calculate_lrc(lrc_covered(spec, &lrc, &chars_read)) ^ data_of(lrc_bits);
+                        chars_read[damaged.position] = rebuilt;
+                        if let (Ok(character), false) = (
+                            decode_character(rebuilt, spec, damaged.position),
+                            Some(rebuilt) == end_sentinel,
+                        ) {
+                            debug!("Rebuilt character {} as {:?}", damaged.position, character);
+                            decoded[damaged.decoded_index] = character;
+                            let info = &mut metadata.characters[damaged.metadata_index];
+                            info.kind = CharacterKind::Data(character);
+                            info.code = parity_bit.map_or(rebuilt, |position| {
+                                insert_parity(rebuilt, position, &spec.parity)
+                            });
+                            metadata.corrected.push(damaged.position);
+                            suspect = None;
+                        }
+                    }
+                    None if !lrc_parity_ok => {
+                        // A single flipped bit in the LRC leaves the data intact
+                        let calculated = calculate_lrc(lrc_covered(spec, &lrc, &chars_read));
+                        if (calculated ^ data_of(lrc_bits)).count_ones() <= 1 {
+                            debug!("Rebuilt the LRC");
+                            metadata.corrected.push(chars_read.len());
+                            lrc_corrected = true;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            if let Some(damaged) = suspect.take() {
+                tolerate(errors, damaged.error())?;
+            }
+
+            let verified = lrc_corrected
+                || match verify_integrity(spec, &chars_read, check_chars.as_deref(), &data_of) {
+                    Ok(()) => true,
+                    Err(error) => {
+                        tolerate(errors, error)?;
+                        false
+                    }
+                };
+
+            if let Some(check_chars) = check_chars.filter(|chars| !chars.is_empty()) {
+                metadata.check_offset = Some(offset);
+                metadata.integrity_present = true;
+                metadata.integrity_checked = verified;
+                for code in check_chars {
+                    metadata.characters.push(CharacterInfo {
+                        offset,
+                        code,
+                        kind: CharacterKind::Check,
+                    });
+                    offset += bits_per_char;
+                }
+            }
+            break;
+        }
+
+        // Decode the character
+        let character = match decode_character(data_bits, spec, position) {
+            Ok(character) if parity_ok => character,
+            Ok(_) => placeholder,
+            Err(error) => tolerate(errors, error).map(|()| placeholder)?,
+        };
+        decoded.push(character);
+        metadata.characters.push(CharacterInfo {
+            offset,
+            code: char_bits,
+            kind: CharacterKind::Data(character),
+        });
+
+        offset += bits_per_char;
+    }
+
+    if spec.end_sentinel.is_some() && !found_end && !policy.allow_missing_end_sentinel {
+        return Err(DecoderError::InvalidEndSentinel);
+    }
+    if let Some(damaged) = suspect {
+        tolerate(errors, damaged.error())?;
+    }
+
+    if decoded.is_empty() {
+        return Err(DecoderError::EmptyTrack);
+    }
+
+    metadata.bit_range = first_offset..offset;
+    metadata.parity_checked = parity_bit.is_some();
+    metadata.leading_clock_bits = count_clock_bits(stream, (0..first_offset).rev(), spec.inverted);
+    metadata.trailing_clock_bits = count_clock_bits(stream, offset..stream.len(), spec.inverted);
+
+    if let Some(maximum) = spec.max_length.filter(|_| policy.enforce_length_limits) {
+        let length = metadata.characters.len();
+        if length > maximum {
+            return Err(DecoderError::TrackTooLong { length, maximum });
+        }
+    }
+    if !policy.allow_trailing_data && offset + metadata.trailing_clock_bits < stream.len() {
+        return Err(DecoderError::TrailingData {
+            offset: offset + metadata.trailing_clock_bits,
+        });
+    }
+    let quality = SwipeQuality::assess(
+        metadata,
+        metadata.parity_checked,
+        spec.integrity != Integrity::None,
+        errors,
+        metadata.corrected.len(),
+    );
+
+    let result: String = decoded.iter().collect();
+    let mut bytes = Vec::new();
+    for &character in &decoded {
+        push_byte(&mut bytes, character, &spec.alphabet);
+    }
+
+    debug!("Decoded successfully: {} characters", decoded.len());
+    trace!("Decoded data: {}", result);
+    Ok(DecodedTrack {
+        data: result,
+        bytes,
+        metadata: std::mem::take(metadata),
+        quality,
+        errors: std::mem::take(errors),
+    })
+}
+
+/// Find the first bit offset holding the start sentinel of a spec with
+/// valid parity
+///
+/// Returns `None` if the spec has no start sentinel or it does not appear.
+pub(crate) fn find_start(stream: &BitStream, spec: &FormatSpec) -> Option<usize> {
+    let start_sentinel = spec.start_sentinel?;
+    let data_of = |char_bits: u8| match spec.parity_bit() {
+        Some(position) => strip_parity(char_bits, position),
+        None => char_bits,
+    };
+    find_start_sentinel(
+        stream,
+        spec.bits_per_char,
+        data_of(start_sentinel),
+        |offset| {
+            let char_bits = read_char(
+                stream,
+                offset,
+                spec.bits_per_char,
+                spec.lsb_first,
+                spec.inverted,
+            )?;
+            check_parity(char_bits, spec.bits_per_char, &spec.parity).then(|| data_of(char_bits))
+        },
+    )
+}
+
+/// A character that failed parity and may be rebuilt from the LRC
+#[derive(Debug, Clone, Copy)]
+struct Suspect {
+    /// Index of the character, counting from the start sentinel
+    position: usize,
+    /// Index of its placeholder among the decoded characters
+    decoded_index: usize,
+    /// Index of its entry in the metadata
+    metadata_index: usize,
+}
+
+impl Suspect {
+    /// The error reported if the character cannot be rebuilt
+    fn error(&self) -> DecoderError {
+        DecoderError::ParityError {
+            position: self.position,
+        }
+    }
+}
+
+/// The characters covered by an LRC
+fn lrc_covered<'a>(spec: &FormatSpec, lrc: &LrcSpec, chars_read: &'a [u8]) -> &'a [u8] {
+    if lrc.include_start_sentinel || spec.start_sentinel.is_none() {
+        chars_read
+    } else {
+        &chars_read[1..]
+    }
+}
+
+/// Verify the check characters read after the end sentinel
+///
+/// Check characters cut off by the end of the stream are not verified.
+pub(crate) fn verify_integrity(
+    spec: &FormatSpec,
+    chars_read: &[u8],
+    check_chars: Option<&[u8]>,
+    data_of: &impl Fn(u8) -> u8,
+) -> Result<(), DecoderError> {
+    let Some(check_chars) = check_chars else {
+        return Ok(());
+    };
+
+    match spec.integrity {
+        Integrity::None => Ok(()),
+        Integrity::Lrc(lrc) => {
+            let calculated_lrc = calculate_lrc(lrc_covered(spec, &lrc, chars_read));
+            let lrc_bits = check_chars[0];
+            debug!(
+                "Calculated LRC: {:b}, LRC bits: {:b}",
+                calculated_lrc, lrc_bits
+            );
+            if check_parity(lrc_bits, spec.bits_per_char, &lrc.parity)
+                && data_of(lrc_bits) == calculated_lrc
+            {
+                Ok(())
+            } else {
+                Err(DecoderError::LrcCheckFailed)
+            }
+        }
+        Integrity::Crc(crc) => {
+            let covered = if crc.include_start_sentinel || spec.start_sentinel.is_none() {
+                chars_read
+            } else {
+                &chars_read[1..]
+            };
+            let expected = calculate_crc(&crc, covered);
+
+            // Reassemble the check value, most significant data bits first
+            let data_bits = u32::from(spec.data_bits_per_char());
+            let mut actual = 0u64;
+            for &char_bits in check_chars {
+                if !check_parity(char_bits, spec.bits_per_char, &spec.parity) {
+                    return Err(DecoderError::ChecksumMismatch {
+                        expected,
+                        actual: actual as u32,
+                    });
+                }
+                actual = (actual << data_bits) | u64::from(data_of(char_bits));
+            }
+            let padding = check_chars.len() as u32 * data_bits - u32::from(crc.width);
+            let actual = (actual >> padding) as u32;
+
+            debug!("Calculated CRC: {:#x}, CRC bits: {:#x}", expected, actual);
+            if actual == expected {
+                Ok(())
+            } else {
+                Err(DecoderError::ChecksumMismatch { expected, actual })
+            }
+        }
+    }
+}
+
+/// Map the data bits of a character to its text representation
+pub(crate) fn decode_character(
+    data_bits: u8,
+    spec: &FormatSpec,
+    position: usize,
+) -> Result<char, DecoderError> {
+    spec.alphabet
+        .decode(data_bits, spec.bits_per_char)
+        .ok_or(DecoderError::InvalidCharacter {
+            position,
+            character: data_bits,
+        })
+}
+
+/// Append a decoded character to the byte payload
+///
+/// Characters from the standard and offset alphabets are single byte values,
+/// which keeps 8-bit data intact. Table characters are stored as UTF-8.
+fn push_byte(bytes: &mut Vec<u8>, character: char, alphabet: &Alphabet) {
+    match (alphabet, u8::try_from(character)) {
+        (Alphabet::Standard | Alphabet::Offset { .. }, Ok(byte)) => bytes.push(byte),
+        _ => bytes.extend_from_slice(character.encode_utf8(&mut [0; 4]).as_bytes()),
+    }
+}
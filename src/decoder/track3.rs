@@ -1,10 +1,15 @@
-use super::track2::decode_track2;
-use crate::{BitStream, DecoderError};
+use super::spec::decode_spec;
+use crate::{BitStream, DecoderError, Encoding, FormatSpec, Track};
 
 /// Decode Track 3 format
 /// Track 3 uses the same encoding as Track 2 (5-bit) but at higher density (210 bpi vs 75 bpi)
 pub fn decode_track3(stream: &BitStream) -> Result<String, DecoderError> {
+    decode_track3_with(stream, &Encoding::default())
+}
+
+/// Decode Track 3 format with arbitrary encoding options
+pub fn decode_track3_with(stream: &BitStream, encoding: &Encoding) -> Result<String, DecoderError> {
     // Track 3 uses the same encoding scheme as Track 2
     // The only difference is the recording density, which doesn't affect decoding logic
-    decode_track2(stream, false, true, false, false, false)
+    decode_spec(stream, &FormatSpec::from_track(Track::Track3, encoding))
 }
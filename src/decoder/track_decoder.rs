@@ -0,0 +1,155 @@
+use crate::{
+    BitStream, DecodeMetadata, DecodePolicy, DecoderError, Format, FormatSpec, SwipeQuality,
+};
+use std::fmt;
+
+/// A decoder for a single track format.
+///
+/// Every [`Format`] implements this trait, and so does every [`FormatSpec`].
+/// Implement it for formats that a [`FormatSpec`] cannot describe, then wrap
+/// the decoder with [`Format::user`] to try it alongside the built-in
+/// formats. Reports, ranking and reversed swipes work the same for both.
+///
+/// The functions in [`common`](super::common) are the building blocks the
+/// built-in formats use to read characters and verify them.
+///
+/// # Example
+///
+/// ```
+/// use magstripe_rs::decoder::common::{check_parity, find_start_sentinel, read_char};
+/// use magstripe_rs::{
+///     BitStream, DecodeMetadata, DecodePolicy, DecodedTrack, Decoder, DecoderError, Format,
+///     ParityType, TrackDecoder,
+/// };
+///
+/// /// Four-bit digits, LSB first with odd parity, after a `1111` marker
+/// #[derive(Debug)]
+/// struct MarkedDigits;
+///
+/// impl TrackDecoder for MarkedDigits {
+///     fn decode(
+///         &self,
+///         stream: &BitStream,
+///         _policy: &DecodePolicy,
+///     ) -> Result<DecodedTrack, DecoderError> {
+///         let start = find_start_sentinel(stream, 4, 0b1111, |offset| read_char(stream, offset, 4, true, false))
+///             .ok_or(DecoderError::InvalidStartSentinel)?;
+///         let mut data = String::new();
+///         let mut offset = start + 4;
+///         while let Some(bits) = read_char(stream, offset, 5, true, false) {
+///             if !check_parity(bits, 5, &ParityType::Odd) {
+///                 break;
+///             }
+///             data.push(char::from(b'0' + (bits & 0b1111)));
+///             offset += 5;
+///         }
+///
+///         let metadata = DecodeMetadata {
+///             start_sentinel_offset: Some(start),
+///             bit_range: start..offset,
+///             parity_checked: true,
+///             ..DecodeMetadata::default()
+///         };
+///         Ok(DecodedTrack::new(data, metadata))
+///     }
+/// }
+///
+/// let formats = vec![Format::Track2, Format::user(MarkedDigits)];
+/// let data = vec![0b11111000, 0];
+/// let output = Decoder::new(&formats).decode(BitStream::new(&data, 9).unwrap()).unwrap();
+/// assert_eq!(output.data, "1");
+/// assert_eq!(output.format, &formats[1]);
+/// ```
+pub trait TrackDecoder: fmt::Debug + Send + Sync {
+    /// Decode a bitstream read in one direction.
+    ///
+    /// The [`Decoder`](crate::Decoder) calls this for the bitstream as
+    /// received and, if enabled, for the reversed bitstream. Return an error
+    /// describing why the bitstream is not in this format; it is recorded in
+    /// the [`DecodeReport`](crate::DecodeReport).
+    fn decode(
+        &self,
+        stream: &BitStream,
+        policy: &DecodePolicy,
+    ) -> Result<DecodedTrack, DecoderError>;
+}
+
+/// The data decoded by a [`TrackDecoder`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedTrack {
+    /// The decoded characters as text.
+    pub data: String,
+
+    /// The decoded characters as bytes, see [`crate::DecoderOutput::bytes`].
+    pub bytes: Vec<u8>,
+
+    /// Where and how the data was found.
+    pub metadata: DecodeMetadata,
+
+    /// How good the read was.
+    pub quality: SwipeQuality,
+
+    /// Errors tolerated by replacing characters with the placeholder.
+    pub errors: Vec<DecoderError>,
+}
+
+impl DecodedTrack {
+    /// Build a decode result from its text and metadata.
+    ///
+    /// The bytes are the UTF-8 encoding of the text, and the quality is
+    /// assessed from the metadata the same way as for the built-in formats.
+    pub fn new(data: impl Into<String>, metadata: DecodeMetadata) -> Self {
+        let data = data.into();
+        let quality = SwipeQuality::assess(
+            &metadata,
+            metadata.parity_checked,
+            metadata.integrity_present,
+            &[],
+            metadata.corrected.len(),
+        );
+        Self {
+            bytes: data.clone().into_bytes(),
+            data,
+            metadata,
+            quality,
+            errors: Vec::new(),
+        }
+    }
+}
+
+/// User decoders are compared by identity, so a format list can still be
+/// searched for the [`Format::User`] it contains.
+impl PartialEq for dyn TrackDecoder + '_ {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(
+            (self as *const Self).cast::<()>(),
+            (other as *const Self).cast::<()>(),
+        )
+    }
+}
+
+impl TrackDecoder for FormatSpec {
+    fn decode(
+        &self,
+        stream: &BitStream,
+        policy: &DecodePolicy,
+    ) -> Result<DecodedTrack, DecoderError> {
+        super::spec::decode_spec_payload(stream, self, policy)
+    }
+}
+
+impl TrackDecoder for Format {
+    fn decode(
+        &self,
+        stream: &BitStream,
+        policy: &DecodePolicy,
+    ) -> Result<DecodedTrack, DecoderError> {
+        match self {
+            Format::User(decoder) => decoder.decode(stream, policy),
+            _ => {
+                let spec = self.spec().expect("every built-in format has a spec");
+                spec.decode(stream, policy)
+            }
+        }
+    }
+}
@@ -0,0 +1,948 @@
+use crate::decoder::common::{calculate_crc, check_parity, insert_parity};
+use crate::{DecoderError, TrackDecoder};
+use std::borrow::Cow;
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+
+/// Represents the various encoding formats used for magnetic stripe cards.
+///
+/// Magnetic stripe cards contain up to three tracks of data, each with different
+/// encoding standards developed by various industries. This enum provides support
+/// for standard formats as well as common variations found in the wild.
+///
+/// # Standards
+///
+/// - **Track 1**: ISO/IEC 7811, developed by IATA (International Air Transport Association)
+/// - **Track 2**: ISO/IEC 7813, developed by ABA (American Bankers Association)  
+/// - **Track 3**: ISO/IEC 4909, developed by the thrift industry
+#[derive(Debug, Clone, PartialEq)]
+pub enum Format {
+    /// Standard ABA Track 2 format (ISO/IEC 7813).
+    ///
+    /// The most common format for financial cards. Uses 5-bit encoding
+    /// (4 data bits + 1 odd parity bit) at 75 bpi density. Characters
+    /// are encoded LSB-first with the character set including digits 0-9
+    /// and symbols `:;<=>?`. Data starts with `;` (start sentinel) and
+    /// ends with `?` (end sentinel), followed by an LRC check character.
+    /// Maximum capacity: 40 characters including sentinels.
+    Track2,
+
+    /// Track 2 format with all bits inverted.
+    ///
+    /// Some readers or cards may invert the magnetic polarity, resulting
+    /// in all bits being flipped (0→1, 1→0). This variant handles such
+    /// inversions while maintaining the same character encoding scheme.
+    Track2Inverted,
+
+    /// Track 2 format with MSB-first bit ordering.
+    ///
+    /// While standard Track 2 uses LSB-first bit order (e.g., decimal 4
+    /// is encoded as `00100`), some implementations use MSB-first ordering
+    /// (decimal 4 would be `00100` read in reverse). The parity bit
+    /// position remains unchanged.
+    Track2MSB,
+
+    /// Explicit LSB-first Track 2 format.
+    ///
+    /// Functionally identical to `Track2` but explicitly specifies LSB-first
+    /// bit ordering. Useful when disambiguating between different bit order
+    /// implementations or when the standard format needs to be explicit.
+    Track2LSB,
+
+    /// Track 2 format without sentinel characters.
+    ///
+    /// Some proprietary systems omit the standard start (`;`) and end (`?`)
+    /// sentinels, encoding only the raw data. This format processes the
+    /// entire magnetic data as payload without looking for framing characters.
+    Track2Raw,
+
+    /// Track 2 format with the parity bit first.
+    ///
+    /// Standard Track 2 places the parity bit as the 5th bit (bit 4 when
+    /// 0-indexed). Some legacy encoders write it as the first bit instead,
+    /// followed by the four data bits LSB-first. Other positions can be
+    /// expressed with [`Encoding::with_parity_position`].
+    Track2SwappedParity,
+
+    /// Track 2 format with even parity instead of odd.
+    ///
+    /// While standard Track 2 uses odd parity, some non-standard
+    /// implementations use even parity. This variant processes Track 2
+    /// data with even parity checking.
+    Track2EvenParity,
+
+    /// Standard IATA Track 1 format (ISO/IEC 7811).
+    ///
+    /// Developed for the airline industry, uses 7-bit encoding (6 data bits
+    /// + 1 odd parity bit) at 210 bpi density. Supports 64 alphanumeric
+    ///   characters including A-Z, 0-9, and special symbols. Data starts
+    ///   with `%` (start sentinel) and ends with `?` (end sentinel), followed
+    ///   by an LRC. Characters are encoded LSB-first with ASCII offset of 32.
+    ///   Maximum capacity: 79 characters including sentinels.
+    Track1,
+
+    /// Track 1 format with all bits inverted.
+    ///
+    /// Handles Track 1 data where magnetic polarity is inverted, similar
+    /// to `Track2Inverted`. Maintains the 7-bit IATA encoding scheme while
+    /// flipping all bit values.
+    Track1Inverted,
+
+    /// Standard Track 3 format (ISO/IEC 4909).
+    ///
+    /// Rarely used track originally designed for the thrift industry with
+    /// read/write capability. Uses 5-bit encoding like Track 2 but at
+    /// 210 bpi density (same as Track 1). Can store up to 107 numeric
+    /// characters. Primarily used in some European countries (notably
+    /// Germany) for additional authorization data, PINs, or account limits.
+    Track3,
+
+    /// An ISO track read with an arbitrary combination of encoding options.
+    ///
+    /// The named variants above are presets over this one; for example
+    /// `Track2Inverted` behaves exactly like
+    /// `Encoded(Track::Track2, Encoding::default().with_inverted(true))`.
+    /// Use it for combinations that have no preset, such as an inverted,
+    /// MSB-first track with even parity.
+    Encoded(Track, Encoding),
+
+    /// Custom format with user-defined specifications.
+    ///
+    /// Allows defining non-standard formats by specifying encoding
+    /// parameters directly. Useful for proprietary card systems, legacy
+    /// formats, or experimental implementations that don't conform to
+    /// ISO standards.
+    Custom(FormatSpec),
+
+    /// A user-defined decoder, for formats a [`FormatSpec`] cannot describe.
+    ///
+    /// Created with [`Format::user`]. Two user formats are equal only if they
+    /// share the same decoder.
+    User(Arc<dyn TrackDecoder>),
+}
+
+impl Format {
+    /// Formats for payment cards: Track 2 and Track 1 in both polarities.
+    ///
+    /// Track 2 comes first because it is present on every financial card
+    /// and its numeric character set rarely decodes noise by accident.
+    /// Polarity is a property of the reader, so each track is tried as
+    /// recorded before it is tried inverted.
+    pub const FINANCIAL: &'static [Format] = &[
+        Format::Track2,
+        Format::Track2Inverted,
+        Format::Track1,
+        Format::Track1Inverted,
+    ];
+
+    /// The three ISO tracks exactly as specified, without any variants.
+    ///
+    /// Ordered as [`Format::FINANCIAL`], with Track 3 last as the least
+    /// common track.
+    pub const ALL_ISO: &'static [Format] = &[Format::Track2, Format::Track1, Format::Track3];
+
+    /// Formats for identity cards such as driving licences.
+    ///
+    /// AAMVA cards carry the holder's name and address on Track 1, so it is
+    /// tried first; Track 3 holds the remaining fields.
+    pub const ID_CARDS: &'static [Format] = &[
+        Format::Track1,
+        Format::Track2,
+        Format::Track3,
+        Format::Track1Inverted,
+        Format::Track2Inverted,
+    ];
+
+    /// Every named format variant, strictest first.
+    ///
+    /// [`Decoder::decode`](crate::Decoder::decode) returns the first format
+    /// that succeeds, so formats that check the most go first: the ISO
+    /// tracks, then reader quirks (inversion and bit order), then encoder
+    /// quirks (parity variants), and [`Format::Track2Raw`] last because
+    /// without sentinels it decodes almost any bitstream.
+    ///
+    /// [`Format::Encoded`] and [`Format::Custom`] take parameters and are
+    /// not included.
+    pub const ALL_VARIANTS: &'static [Format] = &[
+        Format::Track2,
+        Format::Track1,
+        Format::Track3,
+        Format::Track2Inverted,
+        Format::Track1Inverted,
+        Format::Track2LSB,
+        Format::Track2MSB,
+        Format::Track2SwappedParity,
+        Format::Track2EvenParity,
+        Format::Track2Raw,
+    ];
+
+    /// Returns the ISO track and encoding options this format decodes with.
+    ///
+    /// Returns `None` for [`Format::Custom`], which is described by its own
+    /// [`FormatSpec`] instead, and for [`Format::User`].
+    pub fn encoding(&self) -> Option<(Track, Encoding)> {
+        let standard = Encoding::default();
+        let preset = match self {
+            Format::Track2 | Format::Track2LSB => (Track::Track2, standard),
+            Format::Track2Inverted => (Track::Track2, standard.with_inverted(true)),
+            Format::Track2MSB => (Track::Track2, standard.with_bit_order(BitOrder::MsbFirst)),
+            Format::Track2Raw => (Track::Track2, standard.with_framing(Framing::Raw)),
+            Format::Track2SwappedParity => (Track::Track2, standard.with_parity_position(0)),
+            Format::Track2EvenParity => (Track::Track2, standard.with_parity(ParityType::Even)),
+            Format::Track1 => (Track::Track1, standard),
+            Format::Track1Inverted => (Track::Track1, standard.with_inverted(true)),
+            Format::Track3 => (Track::Track3, standard),
+            Format::Encoded(track, encoding) => (*track, *encoding),
+            Format::Custom(_) | Format::User(_) => return None,
+        };
+        Some(preset)
+    }
+
+    /// Returns the format specification this format decodes with.
+    ///
+    /// Every built-in format is a preset [`FormatSpec`], so cloning the spec
+    /// of a standard format and changing one property gives identical
+    /// behaviour for everything else. Returns `None` for [`Format::User`].
+    pub fn spec(&self) -> Option<Cow<'_, FormatSpec>> {
+        match self {
+            Format::Custom(spec) => Some(Cow::Borrowed(spec)),
+            Format::User(_) => None,
+            _ => {
+                let (track, encoding) = self
+                    .encoding()
+                    .expect("every built-in format has an encoding");
+                Some(Cow::Owned(FormatSpec::from_track(track, &encoding)))
+            }
+        }
+    }
+
+    /// Wrap a user-defined decoder so it can be tried alongside the
+    /// built-in formats.
+    pub fn user(decoder: impl TrackDecoder + 'static) -> Self {
+        Format::User(Arc::new(decoder))
+    }
+}
+
+impl From<Box<dyn TrackDecoder>> for Format {
+    fn from(decoder: Box<dyn TrackDecoder>) -> Self {
+        Format::User(Arc::from(decoder))
+    }
+}
+
+/// The three ISO tracks of a magnetic stripe card.
+///
+/// Each track fixes the character width, sentinels and character set;
+/// how those characters are laid out on the wire is described separately
+/// by an [`Encoding`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Track {
+    /// IATA Track 1: 7-bit alphanumeric characters framed by `%` and `?`.
+    Track1,
+
+    /// ABA Track 2: 5-bit numeric characters framed by `;` and `?`.
+    Track2,
+
+    /// Thrift Track 3: 5-bit numeric characters framed by `;` and `?`.
+    Track3,
+}
+
+impl Track {
+    /// Character width and the data bits of the start and end sentinels
+    fn layout(self) -> (u8, u8, u8) {
+        match self {
+            // '%' (0x25 - 0x20) and '?' (0x3F - 0x20)
+            Track::Track1 => (7, 0b000101, 0b011111),
+            // ';' and '?'
+            Track::Track2 | Track::Track3 => (5, 0b1011, 0b1111),
+        }
+    }
+
+    /// The ISO maximum number of characters, including sentinels and LRC
+    pub(crate) fn max_length(self) -> usize {
+        match self {
+            Track::Track1 => 79,
+            Track::Track2 => 40,
+            Track::Track3 => 107,
+        }
+    }
+}
+
+/// The order in which the bits of each character appear on the wire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum BitOrder {
+    /// Least significant bit first, as specified by ISO/IEC 7811.
+    #[default]
+    LsbFirst,
+
+    /// Most significant bit first.
+    MsbFirst,
+}
+
+/// How the payload of a track is delimited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Framing {
+    /// The payload is framed by the track's start and end sentinels and
+    /// followed by an LRC character.
+    #[default]
+    Sentinels,
+
+    /// The whole bitstream is payload, with no sentinels or LRC.
+    Raw,
+}
+
+/// Options describing how the characters of an ISO track are encoded.
+///
+/// The options are independent of each other and apply to Track 1, 2 and 3
+/// alike, so any combination can be expressed. The default is the encoding
+/// specified by ISO/IEC 7811: normal polarity, LSB-first, odd parity in the
+/// most significant bit of each character, framed by sentinels.
+///
+/// # Example
+///
+/// ```
+/// use magstripe_rs::{BitOrder, Encoding, Format, ParityType, Track};
+///
+/// let encoding = Encoding::default()
+///     .with_inverted(true)
+///     .with_bit_order(BitOrder::MsbFirst)
+///     .with_parity(ParityType::Even);
+/// let format = Format::Encoded(Track::Track2, encoding);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Encoding {
+    /// Whether every bit on the wire is inverted (reversed magnetic polarity).
+    pub inverted: bool,
+
+    /// The order of the bits within each character.
+    pub bit_order: BitOrder,
+
+    /// The parity each character, including the LRC, is checked against.
+    pub parity: ParityType,
+
+    /// Whether the payload is framed by sentinels or read raw.
+    pub framing: Framing,
+
+    /// Index of the parity bit within a character once its bit order has been
+    /// normalised, where bit 0 is the least significant data bit.
+    ///
+    /// `None` places the parity bit in the most significant position, as ISO
+    /// does. The remaining bits form the data bits in their original order.
+    pub parity_position: Option<u8>,
+}
+
+impl Default for Encoding {
+    fn default() -> Self {
+        Self {
+            inverted: false,
+            bit_order: BitOrder::LsbFirst,
+            parity: ParityType::Odd,
+            framing: Framing::Sentinels,
+            parity_position: None,
+        }
+    }
+}
+
+impl Encoding {
+    /// Set whether the bits are inverted.
+    pub fn with_inverted(mut self, inverted: bool) -> Self {
+        self.inverted = inverted;
+        self
+    }
+
+    /// Set the bit order of each character.
+    pub fn with_bit_order(mut self, bit_order: BitOrder) -> Self {
+        self.bit_order = bit_order;
+        self
+    }
+
+    /// Set the parity characters are checked against.
+    pub fn with_parity(mut self, parity: ParityType) -> Self {
+        self.parity = parity;
+        self
+    }
+
+    /// Set how the payload is framed.
+    pub fn with_framing(mut self, framing: Framing) -> Self {
+        self.framing = framing;
+        self
+    }
+
+    /// Set the index of the parity bit within each character.
+    pub fn with_parity_position(mut self, parity_position: u8) -> Self {
+        self.parity_position = Some(parity_position);
+        self
+    }
+}
+
+/// A complete description of a character-oriented track format.
+///
+/// Built-in formats are presets over this type (see [`Format::spec`]), and
+/// custom formats use it directly through [`Format::Custom`]. Sentinels are
+/// full character codes, including the parity bit, in the normalised bit
+/// order where the first bit of an LSB-first character is bit 0.
+///
+/// Specs can be written as struct literals, but [`FormatSpec::builder`] and
+/// [`FormatSpec::to_builder`] check them for consistency when they are built.
+///
+/// # Example
+///
+/// ```
+/// use magstripe_rs::FormatSpec;
+///
+/// // Standard Track 2 with every bit inverted
+/// let spec = FormatSpec::track2().to_builder().inverted(true).build().unwrap();
+/// assert_eq!(spec.start_sentinel, Some(0b01011));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatSpec {
+    /// Width of each character in bits, including any parity bit (1 to 8).
+    pub bits_per_char: u8,
+    /// Code of the start sentinel; `None` decodes from the first bit.
+    pub start_sentinel: Option<u8>,
+    /// Code of the end sentinel; `None` decodes to the end of the stream.
+    pub end_sentinel: Option<u8>,
+    /// Whether characters are written least significant bit first.
+    pub lsb_first: bool,
+    /// The parity every character is checked against.
+    pub parity: ParityType,
+    /// Whether every bit on the wire is inverted.
+    pub inverted: bool,
+    /// Index of the parity bit within a character, with the same meaning as
+    /// [`Encoding::parity_position`]. `None` places it in the highest bit.
+    ///
+    /// When `parity` is [`ParityType::None`], a character only has a parity
+    /// bit if this is set; the bit is then skipped without being checked.
+    pub parity_position: Option<u8>,
+    /// The check character that follows the end sentinel, if any.
+    pub integrity: Integrity,
+    /// The mapping from data bits to characters.
+    pub alphabet: Alphabet,
+    /// The maximum number of characters on the track, including the
+    /// sentinels and check characters. Only enforced by
+    /// [`DecodePolicy::enforce_length_limits`](crate::DecodePolicy).
+    pub max_length: Option<usize>,
+}
+
+/// The mapping between the data bits of a character and its text.
+///
+/// The same mapping is used in both directions, see [`Alphabet::decode`] and
+/// [`Alphabet::encode`].
+///
+/// # Example
+///
+/// ```
+/// use magstripe_rs::Alphabet;
+///
+/// // A vendor-specific 5-bit alphabet without easily confused letters
+/// let alphabet = Alphabet::Table("0123456789ABCDEFGHJKLMNPRSTUVWXY".chars().collect());
+/// assert_eq!(alphabet.decode(17, 6), Some('H'));
+/// assert_eq!(alphabet.encode('H', 6), Some(17));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Alphabet {
+    /// The mapping implied by the character width: 5-bit characters map to
+    /// `0x30 + data` as on Track 2, 7-bit characters to `0x20 + data` as on
+    /// Track 1, 8-bit characters are taken as-is, and any other width is
+    /// read as a decimal digit.
+    Standard,
+
+    /// Data bits within `range` map to the character `base + data`.
+    Offset {
+        /// The character code of data value zero.
+        base: u8,
+        /// The data values that are valid characters.
+        range: RangeInclusive<u8>,
+    },
+
+    /// Data bits index into a user-supplied table of characters.
+    Table(Vec<char>),
+}
+
+impl Alphabet {
+    /// The Track 1 alphabet: ASCII 0x20-0x5F (space to underscore).
+    pub fn track1() -> Self {
+        Alphabet::Offset {
+            base: 0x20,
+            range: 0..=0x3F,
+        }
+    }
+
+    /// The Track 2 and Track 3 alphabet: 0-9, :, ;, <, =, >, ?.
+    pub fn track2() -> Self {
+        Alphabet::Offset {
+            base: 0x30,
+            range: 0..=0x0F,
+        }
+    }
+
+    /// Map the data bits of a character of the given width to its text.
+    ///
+    /// Returns `None` if the data bits are not part of the alphabet.
+    pub fn decode(&self, data_bits: u8, bits_per_char: u8) -> Option<char> {
+        match self {
+            Alphabet::Standard => match bits_per_char {
+                5 => Some((0x30 + (data_bits & 0x0F)) as char),
+                7 => Some((0x20 + (data_bits & 0x3F)) as char),
+                8 => Some(data_bits as char),
+                _ => (data_bits <= 9).then(|| (b'0' + data_bits) as char),
+            },
+            Alphabet::Offset { base, range } => range
+                .contains(&data_bits)
+                .then(|| base.checked_add(data_bits))
+                .flatten()
+                .map(char::from),
+            Alphabet::Table(table) => table.get(data_bits as usize).copied(),
+        }
+    }
+
+    /// Map a character to the data bits that encode it, the inverse of
+    /// [`Alphabet::decode`].
+    ///
+    /// Returns `None` if the character is not part of the alphabet.
+    pub fn encode(&self, character: char, bits_per_char: u8) -> Option<u8> {
+        match self {
+            Alphabet::Standard => {
+                let code = u8::try_from(character).ok()?;
+                match bits_per_char {
+                    5 => (0x30..=0x3F).contains(&code).then(|| code - 0x30),
+                    7 => (0x20..=0x5F).contains(&code).then(|| code - 0x20),
+                    8 => Some(code),
+                    _ => character.is_ascii_digit().then(|| code - b'0'),
+                }
+            }
+            Alphabet::Offset { base, range } => {
+                let data_bits = u8::try_from(character).ok()?.checked_sub(*base)?;
+                range.contains(&data_bits).then_some(data_bits)
+            }
+            Alphabet::Table(table) => table
+                .iter()
+                .position(|&c| c == character)
+                .and_then(|index| u8::try_from(index).ok()),
+        }
+    }
+}
+
+/// The integrity check that follows the end sentinel of a track.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Integrity {
+    /// No check character.
+    None,
+
+    /// A longitudinal redundancy check character, as used by the ISO tracks.
+    ///
+    /// A mismatch is reported as [`DecoderError::LrcCheckFailed`].
+    Lrc(LrcSpec),
+
+    /// A cyclic redundancy check spread over one or more check characters.
+    ///
+    /// A mismatch is reported as [`DecoderError::ChecksumMismatch`].
+    Crc(CrcSpec),
+}
+
+impl Integrity {
+    /// The ISO LRC: odd parity, computed from the start sentinel onwards.
+    pub const ISO_LRC: Integrity = Integrity::Lrc(LrcSpec::ISO);
+
+    /// The number of check characters that follow the end sentinel, for
+    /// characters carrying `data_bits` bits of data each.
+    pub fn check_chars(&self, data_bits: u8) -> usize {
+        match self {
+            Integrity::None => 0,
+            Integrity::Lrc(_) => 1,
+            Integrity::Crc(crc) => usize::from(crc.width.div_ceil(data_bits.max(1))),
+        }
+    }
+}
+
+/// Parameters of a longitudinal redundancy check (LRC) character.
+///
+/// The data bits of the LRC are the XOR of the data bits of every character
+/// it covers, which always ends with the end sentinel. The LRC carries its own
+/// parity bit in the format's parity position, checked against `parity`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LrcSpec {
+    /// The parity of the LRC character itself.
+    pub parity: ParityType,
+    /// Whether the start sentinel is part of the XOR.
+    pub include_start_sentinel: bool,
+}
+
+impl LrcSpec {
+    /// The LRC used by ISO/IEC 7811: odd parity, including the start sentinel.
+    pub const ISO: LrcSpec = LrcSpec {
+        parity: ParityType::Odd,
+        include_start_sentinel: true,
+    };
+}
+
+impl Default for LrcSpec {
+    fn default() -> Self {
+        Self::ISO
+    }
+}
+
+/// Parameters of a cyclic redundancy check, in the usual Rocksoft model.
+///
+/// Each covered character contributes one byte holding its data bits. The
+/// check value is stored in the characters after the end sentinel, most
+/// significant data bits first, each character carrying as many data bits as
+/// the format allows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CrcSpec {
+    /// Width of the CRC in bits (1 to 32).
+    pub width: u8,
+    /// The generator polynomial, without its leading term.
+    pub poly: u32,
+    /// Initial value of the register.
+    pub init: u32,
+    /// Whether input bytes and the final value are bit-reflected.
+    pub reflect: bool,
+    /// Value XORed into the final register.
+    pub xor_out: u32,
+    /// Whether the start sentinel is part of the checked data.
+    pub include_start_sentinel: bool,
+}
+
+impl CrcSpec {
+    /// CRC-8 with polynomial 0x07 (CRC-8/SMBUS).
+    pub const CRC8: CrcSpec = CrcSpec {
+        width: 8,
+        poly: 0x07,
+        init: 0x00,
+        reflect: false,
+        xor_out: 0x00,
+        include_start_sentinel: true,
+    };
+
+    /// CRC-16 with polynomial 0x1021 and initial value 0xFFFF
+    /// (CRC-16/CCITT-FALSE).
+    pub const CRC16_CCITT: CrcSpec = CrcSpec {
+        width: 16,
+        poly: 0x1021,
+        init: 0xFFFF,
+        reflect: false,
+        xor_out: 0x0000,
+        include_start_sentinel: true,
+    };
+
+    /// Reflected CRC-16 with polynomial 0x8005 (CRC-16/ARC).
+    pub const CRC16_ARC: CrcSpec = CrcSpec {
+        width: 16,
+        poly: 0x8005,
+        init: 0x0000,
+        reflect: true,
+        xor_out: 0x0000,
+        include_start_sentinel: true,
+    };
+
+    /// Compute the CRC of a sequence of bytes.
+    ///
+    /// Returns [`DecoderError::InvalidCustomFormat`] if the spec fails
+    /// [`CrcSpec::validate`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use magstripe_rs::CrcSpec;
+    ///
+    /// assert_eq!(CrcSpec::CRC16_CCITT.checksum(b"123456789"), Ok(0x29B1));
+    /// ```
+    pub fn checksum(&self, data: &[u8]) -> Result<u32, DecoderError> {
+        self.validate()?;
+        Ok(calculate_crc(self, data))
+    }
+
+    /// Check that the width is between 1 and 32 bits, and that the
+    /// polynomial and initial value fit in it.
+    pub fn validate(&self) -> Result<(), DecoderError> {
+        let invalid = |reason: String| Err(DecoderError::InvalidCustomFormat { reason });
+        if self.width == 0 || self.width > 32 {
+            return invalid(format!("Invalid CRC width: {}", self.width));
+        }
+        if self.width < 32 && (self.poly >> self.width != 0 || self.init >> self.width != 0) {
+            return invalid(format!(
+                "CRC polynomial {:#x} or initial value {:#x} exceeds {} bits",
+                self.poly, self.init, self.width
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl FormatSpec {
+    /// Standard Track 1 (ISO/IEC 7811).
+    pub fn track1() -> Self {
+        Self::from_track(Track::Track1, &Encoding::default())
+    }
+
+    /// Standard Track 2 (ISO/IEC 7813).
+    pub fn track2() -> Self {
+        Self::from_track(Track::Track2, &Encoding::default())
+    }
+
+    /// Standard Track 3 (ISO/IEC 4909).
+    pub fn track3() -> Self {
+        Self::from_track(Track::Track3, &Encoding::default())
+    }
+
+    /// The spec of an ISO track read with the given encoding options.
+    pub fn from_track(track: Track, encoding: &Encoding) -> Self {
+        let (bits_per_char, start_data, end_data) = track.layout();
+        let parity_position = encoding.parity_position.unwrap_or(bits_per_char - 1);
+        let framed = encoding.framing == Framing::Sentinels;
+
+        // Sentinel codes carry the parity bit the encoding expects. An out of
+        // range parity position is kept in the spec and rejected on decode.
+        let code = |data| {
+            insert_parity(
+                data,
+                parity_position.min(bits_per_char - 1),
+                &encoding.parity,
+            )
+        };
+
+        Self {
+            bits_per_char,
+            start_sentinel: framed.then(|| code(start_data)),
+            end_sentinel: framed.then(|| code(end_data)),
+            lsb_first: encoding.bit_order == BitOrder::LsbFirst,
+            parity: encoding.parity,
+            inverted: encoding.inverted,
+            // Without parity checks the position is kept so the bit is skipped
+            parity_position: match encoding.parity {
+                ParityType::None => Some(parity_position),
+                _ => encoding.parity_position,
+            },
+            integrity: if framed {
+                Integrity::Lrc(LrcSpec {
+                    parity: encoding.parity,
+                    include_start_sentinel: true,
+                })
+            } else {
+                Integrity::None
+            },
+            alphabet: match track {
+                Track::Track1 => Alphabet::track1(),
+                Track::Track2 | Track::Track3 => Alphabet::track2(),
+            },
+            max_length: Some(track.max_length()),
+        }
+    }
+
+    /// Start building a spec with the given character width.
+    ///
+    /// The builder starts with no sentinels, LSB-first bit order, no parity,
+    /// no inversion, no integrity check, the standard alphabet and no length
+    /// limit.
+    pub fn builder(bits_per_char: u8) -> FormatSpecBuilder {
+        FormatSpecBuilder {
+            spec: Self {
+                bits_per_char,
+                start_sentinel: None,
+                end_sentinel: None,
+                lsb_first: true,
+                parity: ParityType::None,
+                inverted: false,
+                parity_position: None,
+                integrity: Integrity::None,
+                alphabet: Alphabet::Standard,
+                max_length: None,
+            },
+        }
+    }
+
+    /// Start building a spec from a copy of this one.
+    pub fn to_builder(&self) -> FormatSpecBuilder {
+        FormatSpecBuilder { spec: self.clone() }
+    }
+
+    /// The number of data bits in each character, excluding any parity bit.
+    pub fn data_bits_per_char(&self) -> u8 {
+        self.bits_per_char - u8::from(self.parity_bit().is_some())
+    }
+
+    /// The index of the parity bit, if characters have one.
+    pub fn parity_bit(&self) -> Option<u8> {
+        match self.parity {
+            ParityType::None => self.parity_position,
+            _ => Some(
+                self.parity_position
+                    .unwrap_or(self.bits_per_char.saturating_sub(1)),
+            ),
+        }
+    }
+
+    /// Check that the spec is internally consistent.
+    ///
+    /// Decoding validates the spec first, so a spec written as a struct
+    /// literal fails with the same error the builder would have returned.
+    pub fn validate(&self) -> Result<(), DecoderError> {
+        let invalid = |reason: String| Err(DecoderError::InvalidCustomFormat { reason });
+
+        if self.bits_per_char == 0 || self.bits_per_char > 8 {
+            return invalid(format!("Invalid bits_per_char: {}", self.bits_per_char));
+        }
+
+        if let Some(position) = self.parity_bit() {
+            if self.bits_per_char < 2 {
+                return invalid("A parity bit needs at least 2 bits per character".to_string());
+            }
+            if position >= self.bits_per_char {
+                return invalid(format!(
+                    "Parity position {} outside of {}-bit character",
+                    position, self.bits_per_char
+                ));
+            }
+        }
+
+        for (name, sentinel) in [("Start", self.start_sentinel), ("End", self.end_sentinel)] {
+            let Some(code) = sentinel else { continue };
+            if u16::from(code) >= 1 << self.bits_per_char {
+                return invalid(format!(
+                    "{} sentinel {:#b} does not fit in {} bits",
+                    name, code, self.bits_per_char
+                ));
+            }
+            if !check_parity(code, self.bits_per_char, &self.parity) {
+                return invalid(format!(
+                    "{} sentinel {:#b} does not have {:?} parity",
+                    name, code, self.parity
+                ));
+            }
+        }
+
+        match &self.alphabet {
+            Alphabet::Standard => {}
+            Alphabet::Offset { base, range } => {
+                if range.is_empty() || base.checked_add(*range.end()).is_none() {
+                    return invalid(format!(
+                        "Alphabet offset {:#x} with range {:?} is empty or exceeds 0xFF",
+                        base, range
+                    ));
+                }
+            }
+            Alphabet::Table(table) => {
+                if table.is_empty() {
+                    return invalid("Alphabet table is empty".to_string());
+                }
+            }
+        }
+
+        if self.integrity != Integrity::None && self.end_sentinel.is_none() {
+            return invalid("An integrity check requires an end sentinel".to_string());
+        }
+
+        match self.integrity {
+            Integrity::None => {}
+            Integrity::Lrc(lrc) => {
+                if lrc.parity != ParityType::None && self.parity_bit().is_none() {
+                    return invalid("LRC parity requires a parity bit".to_string());
+                }
+            }
+            Integrity::Crc(crc) => crc.validate()?,
+        }
+
+        Ok(())
+    }
+}
+
+/// Builder for [`FormatSpec`] that validates the spec when it is built.
+///
+/// # Example
+///
+/// ```
+/// use magstripe_rs::{Alphabet, FormatSpec, Integrity, ParityType};
+///
+/// let spec = FormatSpec::builder(5)
+///     .start_sentinel(0b01011)
+///     .end_sentinel(0b11111)
+///     .parity(ParityType::Odd)
+///     .integrity(Integrity::ISO_LRC)
+///     .alphabet(Alphabet::track2())
+///     .max_length(40)
+///     .build()
+///     .unwrap();
+/// assert_eq!(spec, FormatSpec::track2());
+///
+/// // A 5-bit sentinel does not fit a 4-bit character
+/// assert!(spec.to_builder().bits_per_char(4).build().is_err());
+/// ```
+#[derive(Debug, Clone)]
+pub struct FormatSpecBuilder {
+    spec: FormatSpec,
+}
+
+impl FormatSpecBuilder {
+    /// Set the width of each character in bits.
+    pub fn bits_per_char(mut self, bits_per_char: u8) -> Self {
+        self.spec.bits_per_char = bits_per_char;
+        self
+    }
+
+    /// Set the start sentinel code, or `None` to decode from the first bit.
+    pub fn start_sentinel(mut self, start_sentinel: impl Into<Option<u8>>) -> Self {
+        self.spec.start_sentinel = start_sentinel.into();
+        self
+    }
+
+    /// Set the end sentinel code, or `None` to decode to the end of the stream.
+    pub fn end_sentinel(mut self, end_sentinel: impl Into<Option<u8>>) -> Self {
+        self.spec.end_sentinel = end_sentinel.into();
+        self
+    }
+
+    /// Set whether characters are written least significant bit first.
+    pub fn lsb_first(mut self, lsb_first: bool) -> Self {
+        self.spec.lsb_first = lsb_first;
+        self
+    }
+
+    /// Set the parity characters are checked against.
+    pub fn parity(mut self, parity: ParityType) -> Self {
+        self.spec.parity = parity;
+        self
+    }
+
+    /// Set whether every bit is inverted.
+    pub fn inverted(mut self, inverted: bool) -> Self {
+        self.spec.inverted = inverted;
+        self
+    }
+
+    /// Set the index of the parity bit, or `None` for the highest bit.
+    pub fn parity_position(mut self, parity_position: impl Into<Option<u8>>) -> Self {
+        self.spec.parity_position = parity_position.into();
+        self
+    }
+
+    /// Set the check character that follows the end sentinel.
+    pub fn integrity(mut self, integrity: Integrity) -> Self {
+        self.spec.integrity = integrity;
+        self
+    }
+
+    /// Set the mapping from data bits to characters.
+    pub fn alphabet(mut self, alphabet: Alphabet) -> Self {
+        self.spec.alphabet = alphabet;
+        self
+    }
+
+    /// Set the maximum number of characters, or `None` for no limit.
+    pub fn max_length(mut self, max_length: impl Into<Option<usize>>) -> Self {
+        self.spec.max_length = max_length.into();
+        self
+    }
+
+    /// Validate and return the spec.
+    pub fn build(self) -> Result<FormatSpec, DecoderError> {
+        self.spec.validate()?;
+        Ok(self.spec)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ParityType {
+    Odd,
+    Even,
+    None,
+}
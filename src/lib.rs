@@ -1,154 +1,142 @@
 #![doc = include_str!("../README.md")]
 #![allow(clippy::uninlined_format_args)]
 
+mod adaptive;
+mod bitbuf;
 mod bitstream;
+mod candidates;
+mod card;
 pub mod decoder;
+mod format;
+mod metadata;
+mod owned;
+mod packing;
+mod policy;
+mod records;
+mod report;
+mod streaming;
 
+pub use adaptive::AdaptiveDecoder;
+pub use bitbuf::BitBuf;
 pub use bitstream::{BitStream, BitStreamError};
+pub use candidates::{Candidate, DecodeCandidates};
+pub use card::{Card, CardDecoder};
+pub use decoder::{DecodedTrack, TrackDecoder};
+pub use format::{
+    Alphabet, BitOrder, CrcSpec, Encoding, Format, FormatSpec, FormatSpecBuilder, Framing,
+    Integrity, LrcSpec, ParityType, Track,
+};
+pub use metadata::{CharacterInfo, CharacterKind, CheckStatus, DecodeMetadata, SwipeQuality};
+pub use owned::{
+    OwnedCandidate, OwnedDecodeCandidates, OwnedDecoder, OwnedDecoderBuilder, OwnedDecoderOutput,
+};
+pub use packing::BitPacking;
+pub use policy::DecodePolicy;
+pub use records::Records;
+pub use report::{DecodeReport, FormatAttempt};
+pub use streaming::{StreamEvent, StreamingDecoder};
 
-/// Represents the various encoding formats used for magnetic stripe cards.
+/// The direction in which a card was swiped past the read head.
 ///
-/// Magnetic stripe cards contain up to three tracks of data, each with different
-/// encoding standards developed by various industries. This enum provides support
-/// for standard formats as well as common variations found in the wild.
-///
-/// # Standards
-///
-/// - **Track 1**: ISO/IEC 7811, developed by IATA (International Air Transport Association)
-/// - **Track 2**: ISO/IEC 7813, developed by ABA (American Bankers Association)  
-/// - **Track 3**: ISO/IEC 4909, developed by the thrift industry
-#[derive(Debug, Clone, PartialEq)]
-pub enum Format {
-    /// Standard ABA Track 2 format (ISO/IEC 7813).
-    ///
-    /// The most common format for financial cards. Uses 5-bit encoding
-    /// (4 data bits + 1 odd parity bit) at 75 bpi density. Characters
-    /// are encoded LSB-first with the character set including digits 0-9
-    /// and symbols `:;<=>?`. Data starts with `;` (start sentinel) and
-    /// ends with `?` (end sentinel), followed by an LRC check character.
-    /// Maximum capacity: 40 characters including sentinels.
-    Track2,
-
-    /// Track 2 format with all bits inverted.
-    ///
-    /// Some readers or cards may invert the magnetic polarity, resulting
-    /// in all bits being flipped (0→1, 1→0). This variant handles such
-    /// inversions while maintaining the same character encoding scheme.
-    Track2Inverted,
-
-    /// Track 2 format with MSB-first bit ordering.
-    ///
-    /// While standard Track 2 uses LSB-first bit order (e.g., decimal 4
-    /// is encoded as `00100`), some implementations use MSB-first ordering
-    /// (decimal 4 would be `00100` read in reverse). The parity bit
-    /// position remains unchanged.
-    Track2MSB,
-
-    /// Explicit LSB-first Track 2 format.
-    ///
-    /// Functionally identical to `Track2` but explicitly specifies LSB-first
-    /// bit ordering. Useful when disambiguating between different bit order
-    /// implementations or when the standard format needs to be explicit.
-    Track2LSB,
-
-    /// Track 2 format without sentinel characters.
-    ///
-    /// Some proprietary systems omit the standard start (`;`) and end (`?`)
-    /// sentinels, encoding only the raw data. This format processes the
-    /// entire magnetic data as payload without looking for framing characters.
-    Track2Raw,
-
-    /// Track 2 format with parity bit in a different position.
-    ///
-    /// Standard Track 2 places the parity bit as the 5th bit (bit 4 when
-    /// 0-indexed). This variant handles cards where the parity bit is
-    /// placed in a different position within the 5-bit character, which
-    /// can occur due to encoding errors or non-standard implementations.
-    Track2SwappedParity,
-
-    /// Track 2 format with even parity instead of odd.
-    ///
-    /// While standard Track 2 uses odd parity, some non-standard
-    /// implementations use even parity. This variant processes Track 2
-    /// data with even parity checking.
-    Track2EvenParity,
-
-    /// Standard IATA Track 1 format (ISO/IEC 7811).
-    ///
-    /// Developed for the airline industry, uses 7-bit encoding (6 data bits
-    /// + 1 odd parity bit) at 210 bpi density. Supports 64 alphanumeric
-    ///   characters including A-Z, 0-9, and special symbols. Data starts
-    ///   with `%` (start sentinel) and ends with `?` (end sentinel), followed
-    ///   by an LRC. Characters are encoded LSB-first with ASCII offset of 32.
-    ///   Maximum capacity: 79 characters including sentinels.
-    Track1,
-
-    /// Track 1 format with all bits inverted.
-    ///
-    /// Handles Track 1 data where magnetic polarity is inverted, similar
-    /// to `Track2Inverted`. Maintains the 7-bit IATA encoding scheme while
-    /// flipping all bit values.
-    Track1Inverted,
-
-    /// Standard Track 3 format (ISO/IEC 4909).
-    ///
-    /// Rarely used track originally designed for the thrift industry with
-    /// read/write capability. Uses 5-bit encoding like Track 2 but at
-    /// 210 bpi density (same as Track 1). Can store up to 107 numeric
-    /// characters. Primarily used in some European countries (notably
-    /// Germany) for additional authorization data, PINs, or account limits.
-    Track3,
-
-    /// Custom format with user-defined specifications.
-    ///
-    /// Allows defining non-standard formats by specifying encoding
-    /// parameters directly. Useful for proprietary card systems, legacy
-    /// formats, or experimental implementations that don't conform to
-    /// ISO standards.
-    Custom(FormatSpec),
-}
+/// Bidirectional readers deliver the recorded bits end-to-end reversed when
+/// a card is swiped backwards. The decoder reports which orientation of the
+/// bitstream produced the successful decode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SwipeDirection {
+    /// The bits were decoded in the order they were received.
+    Forward,
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct FormatSpec {
-    pub bits_per_char: u8,
-    pub start_sentinel: Option<u8>,
-    pub end_sentinel: Option<u8>,
-    pub lsb_first: bool,
-    pub parity: ParityType,
-    pub inverted: bool,
-}
-
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub enum ParityType {
-    Odd,
-    Even,
-    None,
+    /// The bits were decoded after reversing the stream end-to-end.
+    Reverse,
 }
 
+#[derive(Debug, Clone)]
 pub struct Decoder<'formats> {
     attempt_formats: &'formats [Format],
+    try_reverse: bool,
+    policy: DecodePolicy,
 }
 
+/// Tries [`Format::FINANCIAL`], the same formats as the command line tool,
+/// in both directions with the standard policy.
 impl Default for Decoder<'static> {
     fn default() -> Self {
         Self {
-            attempt_formats: &[Format::Track2],
+            attempt_formats: Format::FINANCIAL,
+            try_reverse: true,
+            policy: DecodePolicy::default(),
         }
     }
 }
 
 /// The result of successfully decoding a magnetic stripe bitstream.
 ///
-/// Contains the decoded data as a string and a reference to the format
-/// that was used to successfully decode the data.
+/// Contains the decoded data as a string and as bytes, a reference to the
+/// format that was used to successfully decode the data, the swipe direction,
+/// the details of where the data was found, and the quality of the read.
 #[derive(Debug, Clone, PartialEq)]
 pub struct DecoderOutput<'a> {
     /// The decoded character data from the magnetic stripe.
+    ///
+    /// For 8-bit formats each byte becomes the character with the same code
+    /// point; use [`DecoderOutput::bytes`] for binary payloads.
     pub data: String,
 
+    /// The decoded payload as bytes.
+    ///
+    /// For formats using the standard or an offset alphabet this is one byte
+    /// per character: the ASCII code for the ISO tracks, and the raw value for
+    /// 8-bit formats. Characters from an [`Alphabet::Table`] are stored as
+    /// UTF-8.
+    pub bytes: Vec<u8>,
+
     /// Reference to the format that successfully decoded the bitstream.
     /// This allows the caller to know which format from the attempted list worked.
     pub format: &'a Format,
+
+    /// Whether the bitstream decoded as received or only after reversing it.
+    pub direction: SwipeDirection,
+
+    /// Where the characters were found in the bitstream and how they were
+    /// checked.
+    pub metadata: DecodeMetadata,
+
+    /// Quality figures for the read, including an overall confidence.
+    pub quality: SwipeQuality,
+
+    /// Errors that were tolerated by replacing bad characters with the
+    /// policy's [`placeholder`](DecodePolicy::placeholder), in stream order.
+    ///
+    /// Always empty unless the decode policy sets a placeholder.
+    pub errors: Vec<DecoderError>,
+}
+
+impl DecoderOutput<'_> {
+    /// The payload as text, if it is printable.
+    ///
+    /// Returns `None` for binary payloads, i.e. when the payload contains
+    /// control characters or bytes that are not valid text on their own.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use magstripe_rs::{BitStream, Decoder, Format};
+    ///
+    /// let data = vec![
+    ///     255, 255, 255, 151, 222, 246, 253, 190, 141, 247, 7, 127, 255, 255, 255, 255, 192,
+    /// ];
+    /// let stream = BitStream::new(&data, 130).unwrap();
+    /// let output = Decoder::new(&[Format::Track2Inverted]).decode(stream).unwrap();
+    /// assert_eq!(output.text(), Some("0004048712"));
+    /// ```
+    pub fn text(&self) -> Option<&str> {
+        printable_text(&self.data, &self.bytes)
+    }
+}
+
+/// The text of a payload, if its bytes are the text and it is printable
+pub(crate) fn printable_text<'a>(data: &'a str, bytes: &[u8]) -> Option<&'a str> {
+    (bytes == data.as_bytes() && !data.chars().any(char::is_control)).then_some(data)
 }
 
 /// Errors that can occur during magnetic stripe decoding.
@@ -163,6 +151,8 @@ pub enum DecoderError {
     NoValidFormat {
         /// The number of formats that were attempted.
         attempted: usize,
+        /// Why each format failed, in the order they were attempted.
+        report: DecodeReport,
     },
 
     /// The bitstream is too short to contain valid data for any format.
@@ -195,6 +185,15 @@ pub enum DecoderError {
     #[error("LRC check failed")]
     LrcCheckFailed,
 
+    /// A CRC check value did not match the data it covers.
+    #[error("Checksum mismatch: expected {expected:#x}, found {actual:#x}")]
+    ChecksumMismatch {
+        /// The check value calculated from the decoded data.
+        expected: u32,
+        /// The check value read from the card.
+        actual: u32,
+    },
+
     /// An invalid character was encountered that doesn't match the format's character set.
     #[error("Invalid character at position {position}: {character:?}")]
     InvalidCharacter {
@@ -204,18 +203,76 @@ pub enum DecoderError {
         character: u8,
     },
 
+    /// The check character was cut off by the end of the stream, and the
+    /// decode policy requires it.
+    #[error("Missing check character")]
+    MissingCheckCharacter,
+
+    /// The sentinels were found, but no data characters lie between them.
+    #[error("Track holds no data characters")]
+    EmptyTrack,
+
+    /// The track has more characters than its format allows.
+    #[error("Track too long: {length} characters, at most {maximum} allowed")]
+    TrackTooLong {
+        /// The number of characters read, including sentinels and check characters.
+        length: usize,
+        /// The maximum allowed by the format.
+        maximum: usize,
+    },
+
+    /// Bits other than clock bits follow the track, and the decode policy
+    /// does not allow them.
+    #[error("Unexpected data after the track at bit {offset}")]
+    TrailingData {
+        /// The offset of the first non-clock bit after the track.
+        offset: usize,
+    },
+
     /// A custom format specification was invalid or incomplete.
     #[error("Invalid custom format specification: {reason}")]
     InvalidCustomFormat {
         /// Description of what was invalid about the custom format.
         reason: String,
     },
+
+    /// A profile imported into an [`AdaptiveDecoder`] could not be read.
+    #[error("Invalid decoder profile: {reason}")]
+    InvalidProfile {
+        /// Description of what was wrong with the profile.
+        reason: String,
+    },
 }
 
 impl<'formats> Decoder<'formats> {
     /// Create a new decoder with the specified formats to attempt
+    ///
+    /// The decoder also tries the reversed bitstream by default, so cards
+    /// swiped in either direction decode. See [`Decoder::with_reverse`].
     pub fn new(attempt_formats: &'formats [Format]) -> Self {
-        Self { attempt_formats }
+        Self {
+            attempt_formats,
+            try_reverse: true,
+            policy: DecodePolicy::default(),
+        }
+    }
+
+    /// Enable or disable decoding of backwards swipes
+    ///
+    /// When enabled, every format is first tried on the bitstream as received,
+    /// and only if all of them fail is each format tried again on the
+    /// reversed bitstream.
+    pub fn with_reverse(mut self, try_reverse: bool) -> Self {
+        self.try_reverse = try_reverse;
+        self
+    }
+
+    /// Set how strictly bitstreams are held to their format
+    ///
+    /// Defaults to [`DecodePolicy::standard`].
+    pub fn with_policy(mut self, policy: DecodePolicy) -> Self {
+        self.policy = policy;
+        self
     }
 
     /// Decode a bitstream using the configured formats
@@ -224,6 +281,77 @@ impl<'formats> Decoder<'formats> {
     /// the decoded data and a reference to the successful format.
     /// If no format succeeds, returns an error indicating the failure.
     pub fn decode(&self, stream: BitStream) -> Result<DecoderOutput<'formats>, DecoderError> {
-        decoder::decode_with_formats(self.attempt_formats, stream)
+        decoder::decode_with_options(self.attempt_formats, stream, self.try_reverse, &self.policy)
+    }
+
+    /// Decode a bitstream, also reporting why each format failed
+    ///
+    /// The report lists every attempt that failed before the result was
+    /// reached, so on success it covers the formats tried before the one that
+    /// worked. On failure it is the same report as in
+    /// [`DecoderError::NoValidFormat`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use magstripe_rs::{BitStream, Decoder, DecoderError, Format};
+    ///
+    /// let data = vec![0u8; 16];
+    /// let stream = BitStream::new(&data, 128).unwrap();
+    /// let formats = vec![Format::Track2, Format::Track1];
+    /// let decoder = Decoder::new(&formats).with_reverse(false);
+    ///
+    /// let (result, report) = decoder.decode_with_report(stream);
+    /// assert!(result.is_err());
+    /// assert_eq!(report.attempts.len(), 2);
+    /// assert_eq!(report.attempts[0].error, DecoderError::InvalidStartSentinel);
+    /// ```
+    pub fn decode_with_report(
+        &self,
+        stream: BitStream,
+    ) -> (Result<DecoderOutput<'formats>, DecoderError>, DecodeReport) {
+        decoder::decode_with_report(self.attempt_formats, stream, self.try_reverse, &self.policy)
+    }
+
+    /// Decode a bitstream with every configured format and rank the results
+    ///
+    /// Unlike [`Decoder::decode`], this does not stop at the first format that
+    /// succeeds. Every format is tried, in both directions if reversed swipes
+    /// are enabled, and the successes are returned best first. Check
+    /// [`DecodeCandidates::ambiguous`] before trusting the best candidate when
+    /// loose formats such as [`Format::Track2Raw`] are in the list.
+    ///
+    /// If no format succeeds, returns [`DecoderError::NoValidFormat`].
+    pub fn decode_all(
+        &self,
+        stream: BitStream,
+    ) -> Result<DecodeCandidates<'formats>, DecoderError> {
+        decoder::decode_all(self.attempt_formats, stream, self.try_reverse, &self.policy)
+    }
+
+    /// Iterate over every record in a bitstream holding several back to back
+    ///
+    /// Some readers buffer several swipes, or several Track 3 records, in a
+    /// single capture. Decoding resumes after the check character of each
+    /// record, so every valid record is yielded in stream order, and a start
+    /// sentinel that leads to no valid record is skipped. Records are only
+    /// read forwards, and trailing data is always allowed since each record
+    /// is followed by the next. The bit offsets in each output index into
+    /// the whole stream.
+    ///
+    /// Use [`Decoder::leftovers`] to find what did not decode.
+    pub fn records<'stream>(&self, stream: BitStream<'stream>) -> Records<'formats, 'stream> {
+        Records::new(self, stream)
+    }
+
+    /// Find the parts of a bitstream that [`Decoder::records`] did not decode
+    ///
+    /// Returns the bit ranges between and around the records, with the runs
+    /// of clock bits at either end trimmed off. A run is only trimmed if its
+    /// bits have the clock value of the neighbouring record's format, taking
+    /// inversion into account. Ranges that hold nothing but clock bits are
+    /// left out, so an empty result means every bit of data was decoded.
+    pub fn leftovers(&self, stream: BitStream) -> Vec<std::ops::Range<usize>> {
+        records::leftovers(self, stream)
     }
 }
@@ -0,0 +1,202 @@
+use crate::DecoderError;
+use std::ops::Range;
+
+/// Details of where and how a bitstream was decoded.
+///
+/// All bit offsets index into the bitstream in the orientation it was decoded
+/// in, i.e. the reversed stream for [`crate::SwipeDirection::Reverse`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DecodeMetadata {
+    /// Bit offset of the start sentinel, if the format has one.
+    pub start_sentinel_offset: Option<usize>,
+
+    /// Bit offset of the end sentinel, if the format has one.
+    pub end_sentinel_offset: Option<usize>,
+
+    /// Bit offset of the first check character (the LRC or CRC), if one
+    /// was read.
+    pub check_offset: Option<usize>,
+
+    /// Every character that was read, from the start sentinel through the
+    /// check characters, in stream order.
+    pub characters: Vec<CharacterInfo>,
+
+    /// Whether every character carried a parity bit that was checked.
+    pub parity_checked: bool,
+
+    /// Whether the check characters were present in the stream.
+    ///
+    /// A check character cut off by the end of the stream is not present.
+    /// Always `false` for formats without an integrity check.
+    pub integrity_present: bool,
+
+    /// Whether the check characters were verified against the data.
+    pub integrity_checked: bool,
+
+    /// The number of clock bits (zeros, or ones for inverted formats) running
+    /// up to the first character.
+    pub leading_clock_bits: usize,
+
+    /// The number of clock bits following the last character.
+    pub trailing_clock_bits: usize,
+
+    /// The bits covered by the characters that were read.
+    pub bit_range: Range<usize>,
+
+    /// Positions of the characters rebuilt from the LRC, counting from the
+    /// start sentinel as [`DecoderError::ParityError`] does.
+    ///
+    /// Always empty unless [`DecodePolicy::correct_errors`](crate::DecodePolicy::correct_errors)
+    /// is set.
+    pub corrected: Vec<usize>,
+}
+
+/// A single character read from the bitstream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CharacterInfo {
+    /// Bit offset of the first bit of the character.
+    pub offset: usize,
+
+    /// The raw code of the character including its parity bit, with the wire
+    /// bit order and any inversion undone (bit 0 is the first bit on the wire).
+    pub code: u8,
+
+    /// The role of the character in the track.
+    pub kind: CharacterKind,
+}
+
+/// The role of a character in a track.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CharacterKind {
+    /// The start sentinel.
+    StartSentinel,
+
+    /// A data character and its decoded text.
+    Data(char),
+
+    /// The end sentinel.
+    EndSentinel,
+
+    /// A check character (LRC or part of a CRC).
+    Check,
+}
+
+/// Quality figures for a single decode.
+///
+/// A bitstream can decode correctly and still be a poor read. Callers can use
+/// [`SwipeQuality::confidence`] to ask for another swipe when it is low.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SwipeQuality {
+    /// The number of clock bits running up to the first character.
+    pub leading_clock_bits: usize,
+
+    /// The number of clock bits following the last character.
+    pub trailing_clock_bits: usize,
+
+    /// Characters that failed their parity check.
+    ///
+    /// A decode stops at the first parity failure unless the decoder is
+    /// configured to tolerate or correct them.
+    pub parity_failures: usize,
+
+    /// Characters that were rebuilt from the LRC.
+    pub parity_recovered: usize,
+
+    /// The state of the check character after the end sentinel.
+    pub check: CheckStatus,
+
+    /// Non-clock bits between the start of the stream and the clock run
+    /// leading up to the start sentinel.
+    ///
+    /// Zero when the sentinel follows nothing but clock bits.
+    pub sentinel_skew: usize,
+
+    /// Overall confidence in the read, from 0.0 to 1.0.
+    pub confidence: f64,
+}
+
+/// The state of the check characters of a decode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CheckStatus {
+    /// The format has no check characters.
+    NotApplicable,
+
+    /// The check characters were cut off by the end of the stream.
+    Missing,
+
+    /// The check characters matched the data.
+    Verified,
+
+    /// The check characters did not match the data. Only reported when the
+    /// decode policy tolerates errors.
+    Failed,
+}
+
+/// The number of clock bits on each side of the data that counts as a clean
+/// lead-in or lead-out.
+const CLEAN_CLOCK_BITS: usize = 8;
+
+impl SwipeQuality {
+    /// Assess a decode from its metadata and the errors it tolerated.
+    ///
+    /// `has_parity` and `has_check` describe the format, so that formats
+    /// without those checks are trusted less.
+    pub(crate) fn assess(
+        metadata: &DecodeMetadata,
+        has_parity: bool,
+        has_check: bool,
+        errors: &[DecoderError],
+        parity_recovered: usize,
+    ) -> Self {
+        let parity_failures = parity_recovered
+            + errors
+                .iter()
+                .filter(|error| matches!(error, DecoderError::ParityError { .. }))
+                .count();
+        let check = if metadata.integrity_checked {
+            CheckStatus::Verified
+        } else if metadata.integrity_present {
+            CheckStatus::Failed
+        } else if has_check {
+            CheckStatus::Missing
+        } else {
+            CheckStatus::NotApplicable
+        };
+        // User decoders may count clock bits that lie outside the bit range
+        let sentinel_skew = metadata
+            .bit_range
+            .start
+            .saturating_sub(metadata.leading_clock_bits);
+
+        let mut confidence = match check {
+            CheckStatus::Verified => 1.0,
+            CheckStatus::Missing => 0.7,
+            CheckStatus::NotApplicable => 0.6,
+            CheckStatus::Failed => 0.3,
+        };
+        if !has_parity {
+            confidence *= 0.8;
+        }
+        let unrecovered = parity_failures - parity_recovered;
+        confidence *= 0.8f64.powi(parity_recovered as i32) * 0.5f64.powi(unrecovered as i32);
+
+        // Short lead-in or lead-out clock runs suggest a clipped or rushed swipe
+        for clock_bits in [metadata.leading_clock_bits, metadata.trailing_clock_bits] {
+            let clean = clock_bits.min(CLEAN_CLOCK_BITS) as f64 / CLEAN_CLOCK_BITS as f64;
+            confidence *= 0.9 + 0.1 * clean;
+        }
+        if sentinel_skew > 0 {
+            confidence *= 0.9;
+        }
+
+        Self {
+            leading_clock_bits: metadata.leading_clock_bits,
+            trailing_clock_bits: metadata.trailing_clock_bits,
+            parity_failures,
+            parity_recovered,
+            check,
+            sentinel_skew,
+            confidence: confidence.clamp(0.0, 1.0),
+        }
+    }
+}
@@ -0,0 +1,278 @@
+use crate::{
+    printable_text, BitStream, Candidate, DecodeCandidates, DecodeMetadata, DecodePolicy,
+    DecodeReport, Decoder, DecoderError, DecoderOutput, Format, SwipeDirection, SwipeQuality,
+};
+use std::sync::Arc;
+
+/// A decoder that owns its list of formats.
+///
+/// Unlike [`Decoder`], an `OwnedDecoder` has no lifetime, so it can be stored
+/// in long-lived structs and shared between threads. Cloning it is cheap: the
+/// formats are shared behind an [`Arc`].
+///
+/// # Example
+///
+/// ```
+/// use magstripe_rs::{BitStream, DecodePolicy, Format, OwnedDecoder};
+/// use std::sync::Arc;
+///
+/// let decoder = Arc::new(
+///     OwnedDecoder::builder()
+///         .formats([Format::Track2, Format::Track2Inverted])
+///         .policy(DecodePolicy::strict())
+///         .build()
+///         .unwrap(),
+/// );
+///
+/// let handler = std::thread::spawn(move || {
+///     let data = vec![
+///         255, 255, 255, 151, 222, 246, 253, 190, 141, 247, 7, 127, 255, 255, 255, 255, 192,
+///     ];
+///     decoder.decode(BitStream::new(&data, 130).unwrap())
+/// });
+/// let output = handler.join().unwrap().unwrap();
+/// assert_eq!(output.format, Format::Track2Inverted);
+/// ```
+#[derive(Debug, Clone)]
+pub struct OwnedDecoder {
+    formats: Arc<[Format]>,
+    try_reverse: bool,
+    policy: DecodePolicy,
+}
+
+/// Tries [`Format::FINANCIAL`], as [`Decoder::default`] does.
+impl Default for OwnedDecoder {
+    fn default() -> Self {
+        Self::new(Format::FINANCIAL)
+    }
+}
+
+impl OwnedDecoder {
+    /// Create a new decoder with the specified formats to attempt
+    ///
+    /// Like [`Decoder::new`], reversed swipes are tried and the standard
+    /// policy is used.
+    pub fn new(formats: impl Into<Arc<[Format]>>) -> Self {
+        Self {
+            formats: formats.into(),
+            try_reverse: true,
+            policy: DecodePolicy::default(),
+        }
+    }
+
+    /// Start building a decoder, which tries [`Format::FINANCIAL`] by default
+    pub fn builder() -> OwnedDecoderBuilder {
+        OwnedDecoderBuilder {
+            decoder: Self::default(),
+        }
+    }
+
+    /// The formats attempted, in order
+    pub fn formats(&self) -> &Arc<[Format]> {
+        &self.formats
+    }
+
+    /// Borrow this decoder as a [`Decoder`]
+    pub fn as_decoder(&self) -> Decoder<'_> {
+        Decoder::new(&self.formats)
+            .with_reverse(self.try_reverse)
+            .with_policy(self.policy)
+    }
+
+    /// Decode a bitstream using the configured formats
+    ///
+    /// See [`Decoder::decode`].
+    pub fn decode(&self, stream: BitStream) -> Result<OwnedDecoderOutput, DecoderError> {
+        self.as_decoder()
+            .decode(stream)
+            .map(DecoderOutput::into_owned)
+    }
+
+    /// Decode a bitstream, also reporting why each format failed
+    ///
+    /// See [`Decoder::decode_with_report`].
+    pub fn decode_with_report(
+        &self,
+        stream: BitStream,
+    ) -> (Result<OwnedDecoderOutput, DecoderError>, DecodeReport) {
+        let (result, report) = self.as_decoder().decode_with_report(stream);
+        (result.map(DecoderOutput::into_owned), report)
+    }
+
+    /// Decode a bitstream with every configured format and rank the results
+    ///
+    /// See [`Decoder::decode_all`].
+    pub fn decode_all(&self, stream: BitStream) -> Result<OwnedDecodeCandidates, DecoderError> {
+        self.as_decoder()
+            .decode_all(stream)
+            .map(DecodeCandidates::into_owned)
+    }
+}
+
+/// Builder for an [`OwnedDecoder`], see [`OwnedDecoder::builder`].
+#[derive(Debug, Clone)]
+pub struct OwnedDecoderBuilder {
+    decoder: OwnedDecoder,
+}
+
+impl OwnedDecoderBuilder {
+    /// Set the formats to attempt, in order.
+    ///
+    /// Passing an existing `Arc<[Format]>` shares it rather than copying.
+    pub fn formats(mut self, formats: impl Into<Arc<[Format]>>) -> Self {
+        self.decoder.formats = formats.into();
+        self
+    }
+
+    /// Set whether backwards swipes are decoded, see [`Decoder::with_reverse`].
+    pub fn reverse(mut self, try_reverse: bool) -> Self {
+        self.decoder.try_reverse = try_reverse;
+        self
+    }
+
+    /// Set how strictly bitstreams are held to their format.
+    pub fn policy(mut self, policy: DecodePolicy) -> Self {
+        self.decoder.policy = policy;
+        self
+    }
+
+    /// Build the decoder, checking the format list.
+    ///
+    /// Returns [`DecoderError::NoFormatsProvided`] for an empty list, and
+    /// [`DecoderError::InvalidCustomFormat`] if a format is not valid.
+    pub fn build(self) -> Result<OwnedDecoder, DecoderError> {
+        if self.decoder.formats.is_empty() {
+            return Err(DecoderError::NoFormatsProvided);
+        }
+        for spec in self.decoder.formats.iter().filter_map(Format::spec) {
+            spec.validate()?;
+        }
+        Ok(self.decoder)
+    }
+}
+
+/// The result of a decode that owns its format.
+///
+/// This is [`DecoderOutput`] without the borrow of the decoder's format list,
+/// made with [`DecoderOutput::into_owned`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct OwnedDecoderOutput {
+    /// The decoded character data, see [`DecoderOutput::data`].
+    pub data: String,
+
+    /// The decoded payload as bytes, see [`DecoderOutput::bytes`].
+    pub bytes: Vec<u8>,
+
+    /// The format that successfully decoded the bitstream.
+    pub format: Format,
+
+    /// Whether the bitstream decoded as received or only after reversing it.
+    pub direction: SwipeDirection,
+
+    /// Where the characters were found in the bitstream and how they were
+    /// checked.
+    pub metadata: DecodeMetadata,
+
+    /// Quality figures for the read, including an overall confidence.
+    pub quality: SwipeQuality,
+
+    /// Errors that were tolerated by replacing bad characters, see
+    /// [`DecoderOutput::errors`].
+    pub errors: Vec<DecoderError>,
+}
+
+impl OwnedDecoderOutput {
+    /// The payload as text, if it is printable, see [`DecoderOutput::text`].
+    pub fn text(&self) -> Option<&str> {
+        printable_text(&self.data, &self.bytes)
+    }
+}
+
+impl DecoderOutput<'_> {
+    /// Detach the output from the decoder's format list.
+    pub fn into_owned(self) -> OwnedDecoderOutput {
+        OwnedDecoderOutput {
+            data: self.data,
+            bytes: self.bytes,
+            format: self.format.clone(),
+            direction: self.direction,
+            metadata: self.metadata,
+            quality: self.quality,
+            errors: self.errors,
+        }
+    }
+}
+
+impl From<DecoderOutput<'_>> for OwnedDecoderOutput {
+    fn from(output: DecoderOutput<'_>) -> Self {
+        output.into_owned()
+    }
+}
+
+/// A scored decode that owns its format, see [`Candidate`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct OwnedCandidate {
+    /// The decoded data and the format that produced it.
+    pub output: OwnedDecoderOutput,
+
+    /// How strongly the bitstream supports this reading, see
+    /// [`Candidate::score`].
+    pub score: u32,
+}
+
+impl OwnedCandidate {
+    /// Whether the reading was confirmed by its check characters, without
+    /// any tolerated errors.
+    pub fn is_clean(&self) -> bool {
+        self.output.metadata.integrity_checked && self.output.errors.is_empty()
+    }
+}
+
+/// Every format that decoded a bitstream, best first, owning their formats.
+///
+/// Returned by [`OwnedDecoder::decode_all`], and made from
+/// [`DecodeCandidates`] with [`DecodeCandidates::into_owned`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct OwnedDecodeCandidates {
+    /// The successful decodes, ordered by descending score. Never empty.
+    pub candidates: Vec<OwnedCandidate>,
+
+    /// Whether two clean candidates decoded to different payloads, see
+    /// [`DecodeCandidates::ambiguous`].
+    pub ambiguous: bool,
+
+    /// Why each of the other attempts failed.
+    pub report: DecodeReport,
+}
+
+impl OwnedDecodeCandidates {
+    /// The highest scoring candidate.
+    pub fn best(&self) -> &OwnedCandidate {
+        &self.candidates[0]
+    }
+}
+
+impl Candidate<'_> {
+    /// Detach the candidate from the decoder's format list.
+    pub fn into_owned(self) -> OwnedCandidate {
+        OwnedCandidate {
+            output: self.output.into_owned(),
+            score: self.score,
+        }
+    }
+}
+
+impl DecodeCandidates<'_> {
+    /// Detach the candidates from the decoder's format list.
+    pub fn into_owned(self) -> OwnedDecodeCandidates {
+        OwnedDecodeCandidates {
+            candidates: self
+                .candidates
+                .into_iter()
+                .map(Candidate::into_owned)
+                .collect(),
+            ambiguous: self.ambiguous,
+            report: self.report,
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use crate::{BitStream, BitStreamError};
+
+/// How a reader lays out the bits of a swipe in its buffer.
+///
+/// [`BitStream`] holds bits MSB-first and left-aligned: the first bit is the
+/// most significant bit of the first byte, and a partly filled final byte
+/// keeps its bits at the top. Readers that deliver another layout can be
+/// repacked with [`BitPacking::unpack`] before decoding, and a stream can be
+/// written back out in any layout with [`BitPacking::pack`].
+///
+/// # Example
+///
+/// ```
+/// use magstripe_rs::{BitPacking, BitStream};
+///
+/// let bits = b"1101011010";
+/// let buffer = BitPacking::Ascii.unpack(bits, bits.len()).unwrap();
+/// let stream = BitStream::new(&buffer, bits.len()).unwrap();
+/// assert_eq!(format!("{:?}", stream), "BitStream(11010110:10)");
+///
+/// // The same bits, least significant bit first in each byte
+/// let lsb_first = BitPacking::LsbFirst.pack(&stream);
+/// assert_eq!(lsb_first, [0b01101011, 0b00000001]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BitPacking {
+    /// The layout of [`BitStream`]: most significant bit first, with the bits
+    /// of a partly filled final byte at the top.
+    MsbFirst,
+
+    /// Least significant bit first, with the bits of a partly filled final
+    /// byte at the bottom.
+    LsbFirst,
+
+    /// Most significant bit first, with the bits of a partly filled final
+    /// byte at the bottom.
+    RightAligned,
+
+    /// One bit per byte, each `0x00` or `0x01`.
+    BitPerByte,
+
+    /// One bit per byte, each the ASCII character `'0'` or `'1'`.
+    Ascii,
+}
+
+impl BitPacking {
+    /// Returns the number of bytes holding `bit_count` bits in this layout.
+    pub fn required_bytes(self, bit_count: usize) -> usize {
+        match self {
+            BitPacking::MsbFirst | BitPacking::LsbFirst | BitPacking::RightAligned => {
+                bit_count.div_ceil(8)
+            }
+            BitPacking::BitPerByte | BitPacking::Ascii => bit_count,
+        }
+    }
+
+    /// Repacks `bit_count` bits from `data` into the left-aligned, MSB-first
+    /// buffer a [`BitStream`] of `bit_count` bits wraps.
+    ///
+    /// Returns an error if `data` is too small to hold `bit_count` bits, or
+    /// if a byte in a one-bit-per-byte layout is not a bit.
+    pub fn unpack(self, data: &[u8], bit_count: usize) -> Result<Vec<u8>, BitStreamError> {
+        let required_bytes = self.required_bytes(bit_count);
+        if data.len() < required_bytes {
+            return Err(BitStreamError::BufferTooSmall {
+                required_bytes,
+                provided_bytes: data.len(),
+            });
+        }
+
+        let mut buffer = vec![0u8; bit_count.div_ceil(8)];
+        for index in 0..bit_count {
+            let bit = self.read_bit(data, bit_count, index)?;
+            buffer[index / 8] |= bit << (7 - index % 8);
+        }
+        Ok(buffer)
+    }
+
+    /// Writes the bits of `stream` out in this layout.
+    ///
+    /// Unused bits in a partly filled final byte are zeroed.
+    pub fn pack(self, stream: &BitStream) -> Vec<u8> {
+        let bit_count = stream.len();
+        let bits = stream.buffer();
+        let bit = |index: usize| (bits[index / 8] >> (7 - index % 8)) & 1;
+
+        let mut packed = vec![0u8; self.required_bytes(bit_count)];
+        for index in 0..bit_count {
+            match self {
+                BitPacking::BitPerByte => packed[index] = bit(index),
+                BitPacking::Ascii => packed[index] = b'0' + bit(index),
+                _ => packed[index / 8] |= bit(index) << self.shift(bit_count, index),
+            }
+        }
+        packed
+    }
+
+    /// Reads bit `index` of `bit_count` from `data`, laid out in this layout
+    fn read_bit(self, data: &[u8], bit_count: usize, index: usize) -> Result<u8, BitStreamError> {
+        match self {
+            BitPacking::BitPerByte => match data[index] {
+                value @ (0 | 1) => Ok(value),
+                value => Err(BitStreamError::InvalidBit {
+                    offset: index,
+                    value,
+                }),
+            },
+            BitPacking::Ascii => match data[index] {
+                value @ (b'0' | b'1') => Ok(value - b'0'),
+                value => Err(BitStreamError::InvalidBit {
+                    offset: index,
+                    value,
+                }),
+            },
+            _ => Ok((data[index / 8] >> self.shift(bit_count, index)) & 1),
+        }
+    }
+
+    /// The position of bit `index` of `bit_count` within its byte, for the
+    /// byte-packed layouts
+    fn shift(self, bit_count: usize, index: usize) -> usize {
+        match self {
+            BitPacking::LsbFirst => index % 8,
+            BitPacking::RightAligned => {
+                // Bits in the final byte count down from the last bit
+                let bits_in_byte = (bit_count - index / 8 * 8).min(8);
+                bits_in_byte - 1 - index % 8
+            }
+            _ => 7 - index % 8,
+        }
+    }
+}
@@ -0,0 +1,135 @@
+/// How strictly a bitstream is held to its format.
+///
+/// The checks that every format always applies (sentinels, parity, the
+/// alphabet and any check character that is present) are not affected. The
+/// policy decides what else is required on top of them, or what may be
+/// missing.
+///
+/// # Example
+///
+/// ```
+/// use magstripe_rs::{DecodePolicy, Decoder, Format};
+///
+/// let formats = vec![Format::Track2];
+/// let decoder = Decoder::new(&formats).with_policy(DecodePolicy::strict());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DecodePolicy {
+    /// Reject tracks whose check character is cut off by the end of the
+    /// stream, for formats that declare one.
+    pub require_check: bool,
+
+    /// Reject tracks longer than the format's
+    /// [`FormatSpec::max_length`](crate::FormatSpec::max_length): 79, 40 and
+    /// 107 characters on ISO tracks 1, 2 and 3.
+    pub enforce_length_limits: bool,
+
+    /// Accept bits other than clock bits after the last character.
+    pub allow_trailing_data: bool,
+
+    /// Accept a track that ends without its end sentinel, at the end of the
+    /// stream or where only clock bits remain.
+    pub allow_missing_end_sentinel: bool,
+
+    /// Keep decoding past bad characters, replacing each one with this
+    /// character.
+    ///
+    /// Parity errors, characters outside the alphabet and a failed check
+    /// character are then collected in [`DecoderOutput::errors`](crate::DecoderOutput::errors)
+    /// instead of failing the decode. `None` fails on the first error.
+    pub placeholder: Option<char>,
+
+    /// Rebuild a single damaged character from the LRC.
+    ///
+    /// The parity bit of each character and the LRC across all characters
+    /// form a 2-D parity. When exactly one character fails parity, its data
+    /// bits are recovered from the LRC; a single flipped bit in the LRC is
+    /// also corrected. The positions are listed in
+    /// [`DecodeMetadata::corrected`](crate::DecodeMetadata::corrected).
+    pub correct_errors: bool,
+}
+
+impl DecodePolicy {
+    /// Require the check character and ISO length limits, and reject
+    /// anything but clock bits after the track. Suited to payments.
+    pub fn strict() -> Self {
+        Self {
+            require_check: true,
+            enforce_length_limits: true,
+            allow_trailing_data: false,
+            allow_missing_end_sentinel: false,
+            placeholder: None,
+            correct_errors: false,
+        }
+    }
+
+    /// Require the end sentinel, and verify the check character when it is
+    /// present. This is the default.
+    pub fn standard() -> Self {
+        Self {
+            require_check: false,
+            enforce_length_limits: false,
+            allow_trailing_data: true,
+            allow_missing_end_sentinel: false,
+            placeholder: None,
+            correct_errors: false,
+        }
+    }
+
+    /// Accept tracks that are cut off before their end sentinel, and replace
+    /// bad characters with U+FFFD instead of failing. Suited to forensic
+    /// tooling and worn cards, where whatever can be read is wanted.
+    pub fn lenient() -> Self {
+        Self {
+            require_check: false,
+            enforce_length_limits: false,
+            allow_trailing_data: true,
+            allow_missing_end_sentinel: true,
+            placeholder: Some(char::REPLACEMENT_CHARACTER),
+            correct_errors: false,
+        }
+    }
+
+    /// Set whether the check character is required.
+    pub fn with_require_check(mut self, require_check: bool) -> Self {
+        self.require_check = require_check;
+        self
+    }
+
+    /// Set whether the format's length limit is enforced.
+    pub fn with_length_limits(mut self, enforce_length_limits: bool) -> Self {
+        self.enforce_length_limits = enforce_length_limits;
+        self
+    }
+
+    /// Set whether non-clock bits are accepted after the track.
+    pub fn with_trailing_data(mut self, allow_trailing_data: bool) -> Self {
+        self.allow_trailing_data = allow_trailing_data;
+        self
+    }
+
+    /// Set whether a missing end sentinel is accepted.
+    pub fn with_missing_end_sentinel(mut self, allow_missing_end_sentinel: bool) -> Self {
+        self.allow_missing_end_sentinel = allow_missing_end_sentinel;
+        self
+    }
+
+    /// Set the character that replaces bad characters, or `None` to fail on
+    /// the first one.
+    pub fn with_placeholder(mut self, placeholder: impl Into<Option<char>>) -> Self {
+        self.placeholder = placeholder.into();
+        self
+    }
+
+    /// Set whether a single damaged character is rebuilt from the LRC.
+    pub fn with_correction(mut self, correct_errors: bool) -> Self {
+        self.correct_errors = correct_errors;
+        self
+    }
+}
+
+impl Default for DecodePolicy {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
@@ -0,0 +1,142 @@
+use crate::decoder::common::extract_bits;
+use crate::decoder::spec::find_start;
+use crate::{BitStream, DecodeMetadata, Decoder, DecoderOutput, Format};
+use std::ops::Range;
+use tracing::debug;
+
+/// An iterator over every record in a bitstream, in stream order.
+///
+/// Returned by [`Decoder::records`]. Each record is decoded as by
+/// [`Decoder::decode`], but only forwards, and the search resumes after the
+/// check character of each record. A start sentinel that does not lead to a
+/// valid record is skipped, as is a decode by a
+/// [`TrackDecoder`](crate::TrackDecoder) that reports an empty
+/// [`bit_range`](crate::DecodeMetadata::bit_range). All bit offsets in the
+/// outputs index into the whole bitstream.
+#[derive(Debug, Clone)]
+pub struct Records<'formats, 'stream> {
+    decoder: Decoder<'formats>,
+    stream: BitStream<'stream>,
+    /// Bit offset the search resumes from
+    position: usize,
+}
+
+impl<'formats, 'stream> Records<'formats, 'stream> {
+    pub(crate) fn new(decoder: &Decoder<'formats>, stream: BitStream<'stream>) -> Self {
+        // Later records are trailing data to the one before them
+        let policy = decoder.policy.with_trailing_data(true);
+        Self {
+            decoder: decoder.clone().with_reverse(false).with_policy(policy),
+            stream,
+            position: 0,
+        }
+    }
+}
+
+impl<'formats> Iterator for Records<'formats, '_> {
+    type Item = DecoderOutput<'formats>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.position < self.stream.len() {
+            let tail_buffer = self.stream.tail_buffer(self.position);
+            let tail = BitStream::new(&tail_buffer, self.stream.len() - self.position)
+                .expect("tail buffer holds the rest of the stream");
+
+            // The record starting first wins, then the best scored. A decode
+            // that does not say which bits it covered cannot be resumed after
+            let record = self
+                .decoder
+                .decode_all(tail.clone())
+                .ok()
+                .and_then(|ranked| {
+                    ranked
+                        .candidates
+                        .into_iter()
+                        .filter(|candidate| !candidate.output.metadata.bit_range.is_empty())
+                        .min_by_key(|candidate| candidate.output.metadata.bit_range.start)
+                });
+            if let Some(candidate) = record {
+                let mut output = candidate.output;
+                shift_metadata(&mut output.metadata, self.position);
+                debug!("Found record at bits {:?}", output.metadata.bit_range);
+                self.position = output.metadata.bit_range.end;
+                return Some(output);
+            }
+
+            // Skip past the first start sentinel, which led nowhere
+            let next_start = self
+                .decoder
+                .attempt_formats
+                .iter()
+                .filter_map(|format| find_start(&tail, &*format.spec()?))
+                .min()?;
+            self.position += next_start + 1;
+        }
+        None
+    }
+}
+
+/// Move every offset in the metadata `by` bits later in the stream
+pub(crate) fn shift_metadata(metadata: &mut DecodeMetadata, by: usize) {
+    for offset in [
+        &mut metadata.start_sentinel_offset,
+        &mut metadata.end_sentinel_offset,
+        &mut metadata.check_offset,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        *offset += by;
+    }
+    for character in &mut metadata.characters {
+        character.offset += by;
+    }
+    metadata.bit_range = metadata.bit_range.start + by..metadata.bit_range.end + by;
+}
+
+/// The bit ranges not covered by any record that hold more than clock bits
+pub(crate) fn leftovers(decoder: &Decoder, stream: BitStream) -> Vec<Range<usize>> {
+    // Each gap, with the clock value of the records before and after it
+    let mut gaps = Vec::new();
+    let mut gap_start = 0;
+    let mut clock_before = None;
+    for record in Records::new(decoder, stream.clone()) {
+        let clock = clock_value(std::slice::from_ref(record.format));
+        gaps.push((
+            gap_start..record.metadata.bit_range.start,
+            clock_before.unwrap_or(clock),
+            clock,
+        ));
+        gap_start = record.metadata.bit_range.end;
+        clock_before = Some(clock);
+    }
+    let clock_after = clock_before.unwrap_or_else(|| clock_value(decoder.attempt_formats));
+    gaps.push((gap_start..stream.len(), clock_after, clock_after));
+
+    // Clock bits run away from a record and up to the next one, so trim the
+    // runs of clock bits at either end of each gap
+    let bit = |offset: usize| extract_bits(&stream, offset, 1);
+    gaps.into_iter()
+        .filter(|(gap, _, _)| !gap.is_empty())
+        .filter_map(|(gap, leading, trailing)| {
+            let leading = leading.or(bit(gap.start));
+            let trailing = trailing.or(bit(gap.end - 1));
+            let start = gap.clone().find(|&offset| bit(offset) != leading)?;
+            let end = gap.rev().find(|&offset| bit(offset) != trailing)? + 1;
+            Some(start..end)
+        })
+        .collect()
+}
+
+/// The value of the clock bits shared by `formats`
+///
+/// Returns `None` if the formats clock with different values, or any of
+/// them has no [`FormatSpec`](crate::FormatSpec) to say, in which case a run
+/// of either value is taken for clock bits.
+fn clock_value(formats: &[Format]) -> Option<u8> {
+    let mut values = formats
+        .iter()
+        .map(|format| format.spec().map(|spec| u8::from(spec.inverted)));
+    let first = values.next()??;
+    values.all(|value| value == Some(first)).then_some(first)
+}
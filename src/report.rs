@@ -0,0 +1,61 @@
+use crate::{DecoderError, Format, SwipeDirection};
+use std::fmt;
+
+/// Why each attempted format failed to decode a bitstream.
+///
+/// Returned inside [`DecoderError::NoValidFormat`] and by
+/// [`crate::Decoder::decode_with_report`]. Attempts are listed in the order
+/// they were made: every format on the stream as received, then every format
+/// on the reversed stream.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DecodeReport {
+    /// One entry per failed attempt.
+    pub attempts: Vec<FormatAttempt>,
+}
+
+/// A single failed attempt to decode a bitstream with one format.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatAttempt {
+    /// The format that was attempted.
+    pub format: Format,
+
+    /// The orientation of the bitstream the format was attempted on.
+    pub direction: SwipeDirection,
+
+    /// Why the format failed.
+    pub error: DecoderError,
+}
+
+impl DecodeReport {
+    /// The failed attempts for a given format, in either direction.
+    pub fn attempts_for<'a>(
+        &'a self,
+        format: &'a Format,
+    ) -> impl Iterator<Item = &'a FormatAttempt> + 'a {
+        self.attempts
+            .iter()
+            .filter(move |attempt| &attempt.format == format)
+    }
+}
+
+impl fmt::Display for DecodeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, attempt) in self.attempts.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", attempt)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for FormatAttempt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} ({:?}): {}",
+            self.format, self.direction, self.error
+        )
+    }
+}
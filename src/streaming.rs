@@ -0,0 +1,491 @@
+use crate::decoder::common::check_parity;
+use crate::decoder::spec::{decode_spec_progress, Progress};
+use crate::decoder::DecodedTrack;
+use crate::records::shift_metadata;
+use crate::{
+    BitBuf, BitStream, BitStreamError, CharacterKind, DecodePolicy, Decoder, DecoderError,
+    DecoderOutput, Format, FormatSpec, Integrity,
+};
+use tracing::debug;
+
+/// Something the [`StreamingDecoder`] found while bits were arriving.
+///
+/// Events follow the forward direction of the stream only; a card swiped
+/// backwards shows up in [`StreamingDecoder::finish`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamEvent<'formats> {
+    /// A start sentinel was found and the decoder locked onto its format.
+    StartSentinel {
+        /// The format whose start sentinel was found.
+        format: &'formats Format,
+        /// Bit offset of the start sentinel.
+        offset: usize,
+    },
+
+    /// A data character was decoded.
+    Character {
+        /// Index of the character, counting from the start sentinel as
+        /// [`DecoderError::ParityError`] does.
+        position: usize,
+        /// The decoded character.
+        character: char,
+        /// Bit offset of the first bit of the character.
+        offset: usize,
+    },
+
+    /// A character could not be decoded.
+    ///
+    /// If the decode policy sets a
+    /// [`placeholder`](crate::DecodePolicy::placeholder), a `Character`
+    /// event with the placeholder follows. Otherwise the locked format has
+    /// failed and [`StreamEvent::LockLost`] follows.
+    CharacterError {
+        /// Bit offset of the first bit of the character.
+        offset: usize,
+        /// Why the character could not be decoded.
+        error: DecoderError,
+    },
+
+    /// The end sentinel was found.
+    EndSentinel {
+        /// Bit offset of the end sentinel.
+        offset: usize,
+    },
+
+    /// The check characters matched the data.
+    CheckVerified {
+        /// Bit offset of the first check character.
+        offset: usize,
+    },
+
+    /// The check characters did not match the data.
+    ///
+    /// Unless the decode policy sets a
+    /// [`placeholder`](crate::DecodePolicy::placeholder), the locked format
+    /// has failed and [`StreamEvent::LockLost`] follows.
+    CheckFailed {
+        /// Bit offset of the first check character.
+        offset: usize,
+        /// The mismatch that was found.
+        error: DecoderError,
+    },
+
+    /// The decoder dropped the format it was locked onto.
+    ///
+    /// The events since its `StartSentinel` do not belong to the swipe. The
+    /// search goes on with the other formats, so another `StartSentinel` may
+    /// follow.
+    LockLost {
+        /// The format that was dropped.
+        format: &'formats Format,
+        /// Why the format failed, or `None` if a format earlier in the list
+        /// has since found its own start sentinel and taken over.
+        error: Option<DecoderError>,
+    },
+
+    /// Every character of the swipe, including the check characters, has
+    /// been read.
+    SwipeComplete,
+}
+
+/// A decoder fed with bits as they arrive from the read head.
+///
+/// Bits are appended with [`StreamingDecoder::push_bits`], which reports
+/// progress as [`StreamEvent`]s. The events follow the format that
+/// [`Decoder::decode`] would settle on for the bits received so far: a
+/// format is only dropped once it has failed in a way more bits cannot
+/// change, and a format earlier in the list takes over as soon as its start
+/// sentinel appears, each announced by [`StreamEvent::LockLost`]. Formats
+/// without a [`FormatSpec`], such as user decoders, are not followed.
+///
+/// Trailing data after a swipe never fails the stream, whatever the decode
+/// policy. The result of [`StreamingDecoder::finish`] is always the same as
+/// decoding the accumulated bits with the [`Decoder`] in one go.
+///
+/// # Example
+///
+/// ```
+/// use magstripe_rs::{Decoder, Format, StreamEvent, StreamingDecoder};
+///
+/// let data = vec![
+///     255, 255, 255, 151, 222, 246, 253, 190, 141, 247, 7, 127, 255, 255, 255, 255, 192,
+/// ];
+/// let formats = [Format::Track2Inverted];
+/// let mut streaming = StreamingDecoder::new(Decoder::new(&formats));
+///
+/// let mut events = Vec::new();
+/// for chunk in data.chunks(4) {
+///     let nbits = (chunk.len() * 8).min(130 - streaming.len());
+///     events.extend(streaming.push_bits(chunk, nbits).unwrap());
+/// }
+///
+/// assert_eq!(events.last(), Some(&StreamEvent::SwipeComplete));
+/// assert_eq!(streaming.finish().unwrap().data, "0004048712");
+/// ```
+#[derive(Debug, Clone)]
+pub struct StreamingDecoder<'formats> {
+    decoder: Decoder<'formats>,
+    bits: BitBuf,
+    /// Formats that have failed for good, by index into the format list
+    failed: Vec<bool>,
+    /// Bit offset each format is decoded from, by index into the format
+    /// list: its start sentinel once found, and before that the first offset
+    /// not yet searched for one
+    resume: Vec<usize>,
+    state: State<'formats>,
+}
+
+/// Progress through the swipe
+#[derive(Debug, Clone)]
+enum State<'formats> {
+    /// Looking for a start sentinel
+    Searching,
+    /// Reading characters of the locked format
+    Locked(Lock<'formats>),
+    /// The swipe is complete, or every format has failed
+    Idle,
+}
+
+/// The format a swipe has locked onto
+#[derive(Debug, Clone)]
+struct Lock<'formats> {
+    /// Index of the format in the format list
+    index: usize,
+    format: &'formats Format,
+    /// The number of characters already reported
+    reported: usize,
+}
+
+/// How far a format got with the bits received so far
+enum Outcome {
+    /// The start sentinel has not arrived yet, and cannot start before the
+    /// `searched` bit offset
+    Waiting { searched: usize },
+    /// The track has started, and more bits may finish it
+    Reading(Progress),
+    /// The track was read through to its check characters
+    Complete(DecodedTrack),
+    /// The track failed in a way more bits cannot change
+    Failed(Progress, DecoderError),
+}
+
+impl<'formats> StreamingDecoder<'formats> {
+    /// Create a streaming decoder using the formats and policy of `decoder`
+    pub fn new(decoder: Decoder<'formats>) -> Self {
+        Self {
+            failed: vec![false; decoder.attempt_formats.len()],
+            resume: vec![0; decoder.attempt_formats.len()],
+            decoder,
+            bits: BitBuf::new(),
+            state: State::Searching,
+        }
+    }
+
+    /// The number of bits received so far
+    pub fn len(&self) -> usize {
+        self.bits.len()
+    }
+
+    /// Whether no bits have been received yet
+    pub fn is_empty(&self) -> bool {
+        self.bits.is_empty()
+    }
+
+    /// The bits received so far
+    pub fn stream(&self) -> BitStream<'_> {
+        self.bits.as_stream()
+    }
+
+    /// Append the first `nbits` bits of `data`, MSB first, and report what
+    /// they revealed
+    ///
+    /// Each call decodes the format being followed again from its start
+    /// sentinel, and searches the others for a start sentinel only in the
+    /// bits not searched before, so the work per call grows with the length
+    /// of the track rather than of everything received. Once the swipe is
+    /// complete, or every format has failed, further bits are only stored.
+    ///
+    /// Returns an error if `data` is too small to hold `nbits` bits.
+    pub fn push_bits(
+        &mut self,
+        data: &[u8],
+        nbits: usize,
+    ) -> Result<Vec<StreamEvent<'formats>>, BitStreamError> {
+        self.bits.extend_from_stream(&BitStream::new(data, nbits)?);
+        Ok(self.advance())
+    }
+
+    /// Whether no more events will be emitted, because the swipe was read
+    /// through to its check characters or every format has failed
+    pub fn is_complete(&self) -> bool {
+        matches!(self.state, State::Idle)
+    }
+
+    /// Decode every bit received so far
+    ///
+    /// This is exactly [`Decoder::decode`] of the accumulated bits, including
+    /// reversed swipes, whatever events were reported along the way.
+    pub fn finish(&self) -> Result<DecoderOutput<'formats>, DecoderError> {
+        self.decoder.decode(self.stream())
+    }
+
+    /// Decode the bits received so far with each format, in list order, and
+    /// report the progress of the first one that has started
+    fn advance(&mut self) -> Vec<StreamEvent<'formats>> {
+        let stream = self.bits.as_stream();
+        let policy = self.decoder.policy.with_trailing_data(true);
+        let mut events = Vec::new();
+
+        while !matches!(self.state, State::Idle) {
+            let mut waiting = false;
+            let mut current = None;
+            for (index, format) in self.decoder.attempt_formats.iter().enumerate() {
+                if self.failed[index] {
+                    continue;
+                }
+                let Some(spec) = format.spec() else {
+                    continue;
+                };
+                match attempt(&stream, self.resume[index], &spec, &policy) {
+                    Outcome::Waiting { searched } => {
+                        self.resume[index] = self.resume[index].max(searched);
+                        waiting = true;
+                    }
+                    Outcome::Failed(progress, error) if progress.metadata.characters.is_empty() => {
+                        debug!("{:?} failed before it started: {}", format, error);
+                        self.failed[index] = true;
+                    }
+                    outcome => {
+                        if let Some(start) = outcome.start_sentinel_offset() {
+                            self.resume[index] = start;
+                        }
+                        current = Some((index, format, spec.into_owned(), outcome));
+                        break;
+                    }
+                }
+            }
+
+            let Some((index, format, spec, outcome)) = current else {
+                if !waiting {
+                    debug!("Every format has failed");
+                    self.state = State::Idle;
+                }
+                break;
+            };
+
+            let reported = match &self.state {
+                State::Locked(lock) if lock.index == index => lock.reported,
+                State::Locked(lock) => {
+                    debug!("{:?} takes over from {:?}", format, lock.format);
+                    events.push(StreamEvent::LockLost {
+                        format: lock.format,
+                        error: None,
+                    });
+                    0
+                }
+                _ => 0,
+            };
+            if reported == 0 {
+                debug!("Locked onto {:?}", format);
+            }
+
+            match outcome {
+                Outcome::Reading(progress) => {
+                    let settled = settled_characters(&progress, &spec, &policy);
+                    report(&mut events, format, &progress, reported..settled);
+                    self.state = State::Locked(Lock {
+                        index,
+                        format,
+                        reported: settled.max(reported),
+                    });
+                    break;
+                }
+                Outcome::Complete(track) => {
+                    let progress = Progress {
+                        metadata: track.metadata,
+                        errors: track.errors,
+                    };
+                    let count = progress.metadata.characters.len();
+                    report(&mut events, format, &progress, reported..count);
+                    events.push(StreamEvent::SwipeComplete);
+                    self.state = State::Idle;
+                }
+                Outcome::Failed(progress, error) => {
+                    debug!("Lost {:?}: {}", format, error);
+                    let bits_per_char = spec.bits_per_char as usize;
+                    let first_offset = progress.metadata.start_sentinel_offset.unwrap_or(0);
+                    let count = progress.metadata.characters.len();
+                    match error {
+                        DecoderError::ParityError { position }
+                        | DecoderError::InvalidCharacter { position, .. } => {
+                            report(&mut events, format, &progress, reported..position);
+                            events.push(StreamEvent::CharacterError {
+                                offset: first_offset + position * bits_per_char,
+                                error: error.clone(),
+                            });
+                        }
+                        DecoderError::LrcCheckFailed | DecoderError::ChecksumMismatch { .. } => {
+                            report(&mut events, format, &progress, reported..count);
+                            let end_offset = progress.metadata.end_sentinel_offset.unwrap_or(0);
+                            events.push(StreamEvent::CheckFailed {
+                                offset: end_offset + bits_per_char,
+                                error: error.clone(),
+                            });
+                        }
+                        _ => report(&mut events, format, &progress, reported..count),
+                    }
+                    events.push(StreamEvent::LockLost {
+                        format,
+                        error: Some(error),
+                    });
+                    self.failed[index] = true;
+                    self.state = State::Searching;
+                }
+                Outcome::Waiting { .. } => unreachable!("formats still waiting are skipped"),
+            }
+        }
+        events
+    }
+}
+
+impl Outcome {
+    /// Bit offset of the start sentinel, once it has been found
+    fn start_sentinel_offset(&self) -> Option<usize> {
+        match self {
+            Outcome::Waiting { .. } => None,
+            Outcome::Reading(progress) | Outcome::Failed(progress, _) => {
+                progress.metadata.start_sentinel_offset
+            }
+            Outcome::Complete(track) => track.metadata.start_sentinel_offset,
+        }
+    }
+}
+
+/// Decode the bits received so far from bit offset `from` with one format
+///
+/// No start sentinel of the format may begin before `from`, so the decode
+/// finds the same one a decode of the whole stream would.
+fn attempt(stream: &BitStream, from: usize, spec: &FormatSpec, policy: &DecodePolicy) -> Outcome {
+    let tail_buffer = stream.tail_buffer(from);
+    let tail = BitStream::new(&tail_buffer, stream.len() - from)
+        .expect("tail buffer holds the rest of the stream");
+
+    let mut progress = Progress::default();
+    let result = decode_spec_progress(&tail, spec, policy, &mut progress);
+    shift_metadata(&mut progress.metadata, from);
+    match result {
+        Ok(mut track) => {
+            shift_metadata(&mut track.metadata, from);
+            let check_count = spec.integrity.check_chars(spec.data_bits_per_char());
+            let complete = track.metadata.end_sentinel_offset.is_some()
+                && (check_count == 0 || track.metadata.check_offset.is_some());
+            if complete {
+                Outcome::Complete(track)
+            } else {
+                Outcome::Reading(Progress {
+                    metadata: track.metadata,
+                    errors: track.errors,
+                })
+            }
+        }
+        // Every offset a start sentinel fits at has been searched
+        Err(DecoderError::InvalidStartSentinel) => Outcome::Waiting {
+            searched: stream.len() + 1 - usize::from(spec.bits_per_char),
+        },
+        Err(DecoderError::BitstreamTooShort { .. }) if progress.metadata.characters.is_empty() => {
+            Outcome::Waiting { searched: from }
+        }
+        // Errors after which a character, the end sentinel or the check
+        // characters can still arrive
+        Err(
+            DecoderError::BitstreamTooShort { .. }
+            | DecoderError::InvalidEndSentinel
+            | DecoderError::MissingCheckCharacter,
+        ) => Outcome::Reading(progress),
+        // A track cut short by clock bits may go on once they are followed
+        // by data
+        Err(DecoderError::EmptyTrack) if progress.metadata.end_sentinel_offset.is_none() => {
+            Outcome::Reading(progress)
+        }
+        Err(error) => Outcome::Failed(progress, error),
+    }
+}
+
+/// The number of characters of an unfinished track that more bits cannot
+/// change
+///
+/// A character failing parity may still be rebuilt from the LRC once it
+/// arrives, so nothing from the first such character on is settled.
+fn settled_characters(progress: &Progress, spec: &FormatSpec, policy: &DecodePolicy) -> usize {
+    let characters = &progress.metadata.characters;
+    if !policy.correct_errors || !matches!(spec.integrity, Integrity::Lrc(_)) {
+        return characters.len();
+    }
+    characters
+        .iter()
+        .position(|info| {
+            matches!(info.kind, CharacterKind::Data(_))
+                && !check_parity(info.code, spec.bits_per_char, &spec.parity)
+        })
+        .unwrap_or(characters.len())
+}
+
+/// Emit the events for the characters in `range`
+fn report<'formats>(
+    events: &mut Vec<StreamEvent<'formats>>,
+    format: &'formats Format,
+    progress: &Progress,
+    range: std::ops::Range<usize>,
+) {
+    let metadata = &progress.metadata;
+    let Some(characters) = metadata.characters.get(range.clone()) else {
+        return;
+    };
+    for (position, info) in range.zip(characters) {
+        let offset = info.offset;
+        match info.kind {
+            CharacterKind::StartSentinel => {
+                events.push(StreamEvent::StartSentinel { format, offset })
+            }
+            CharacterKind::Data(character) => {
+                let tolerated = progress.errors.iter().find(|error| {
+                    matches!(error,
+                        DecoderError::ParityError { position: p }
+                        | DecoderError::InvalidCharacter { position: p, .. } if *p == position)
+                });
+                if let Some(error) = tolerated {
+                    events.push(StreamEvent::CharacterError {
+                        offset,
+                        error: error.clone(),
+                    });
+                }
+                events.push(StreamEvent::Character {
+                    position,
+                    character,
+                    offset,
+                });
+            }
+            CharacterKind::EndSentinel => events.push(StreamEvent::EndSentinel { offset }),
+            CharacterKind::Check if Some(offset) == metadata.check_offset => {
+                if metadata.integrity_checked {
+                    events.push(StreamEvent::CheckVerified { offset });
+                } else {
+                    let error = progress
+                        .errors
+                        .iter()
+                        .find(|error| {
+                            matches!(
+                                error,
+                                DecoderError::LrcCheckFailed
+                                    | DecoderError::ChecksumMismatch { .. }
+                            )
+                        })
+                        .cloned()
+                        .unwrap_or(DecoderError::LrcCheckFailed);
+                    events.push(StreamEvent::CheckFailed { offset, error });
+                }
+            }
+            CharacterKind::Check => {}
+        }
+    }
+}
@@ -0,0 +1,180 @@
+mod common;
+
+use common::{CARD, CARD_BITS};
+use magstripe_rs::{
+    AdaptiveDecoder, BitStream, DecodeMetadata, DecodePolicy, DecodedTrack, DecoderError, Format,
+    SwipeDirection, TrackDecoder,
+};
+use std::fmt;
+
+/// Test that the successful format moves to the front and stays there
+#[test]
+fn test_adaptive_learns_format_order() {
+    let mut decoder = AdaptiveDecoder::new(Format::ALL_VARIANTS);
+    assert_eq!(decoder.formats(), Format::ALL_VARIANTS);
+
+    for _ in 0..3 {
+        let output = decoder
+            .decode(BitStream::new(&CARD, CARD_BITS).unwrap())
+            .unwrap();
+        assert_eq!(output.data, "0004048712");
+        assert_eq!(output.format, Format::Track2Inverted);
+        assert_eq!(output.direction, SwipeDirection::Forward);
+    }
+    let garbage = [0xA5u8; 4];
+    assert!(decoder
+        .decode(BitStream::new(&garbage, 32).unwrap())
+        .is_err());
+
+    assert_eq!(decoder.swipes(), 4);
+    assert_eq!(decoder.successes(&Format::Track2Inverted), 3);
+    assert_eq!(decoder.successes(&Format::Track2), 0);
+    assert_eq!(decoder.formats()[0], Format::Track2Inverted);
+
+    // Formats that never succeeded keep their configured order
+    let rest: Vec<&Format> = Format::ALL_VARIANTS
+        .iter()
+        .filter(|f| **f != Format::Track2Inverted)
+        .collect();
+    assert_eq!(decoder.formats()[1..].iter().collect::<Vec<_>>(), rest);
+
+    decoder.reset();
+    assert_eq!(decoder.formats(), Format::ALL_VARIANTS);
+    assert_eq!(decoder.swipes(), 0);
+}
+
+/// Test that an exported profile restores the learned order
+#[test]
+fn test_adaptive_profile_round_trip() {
+    let mut decoder = AdaptiveDecoder::new(Format::FINANCIAL);
+    decoder
+        .decode(BitStream::new(&CARD, CARD_BITS).unwrap())
+        .unwrap();
+    let profile = decoder.export_profile();
+
+    let json: serde_json::Value = serde_json::from_str(&profile).unwrap();
+    assert_eq!(json["swipes"], 1);
+    assert_eq!(json["formats"][0]["format"], "Track2Inverted");
+    assert_eq!(json["formats"][0]["successes"], 1);
+
+    let mut restarted = AdaptiveDecoder::new(Format::FINANCIAL);
+    assert!(restarted.import_profile(&profile).unwrap().is_empty());
+    assert_eq!(restarted.formats(), decoder.formats());
+    assert_eq!(restarted.swipes(), 1);
+    assert_eq!(restarted.export_profile(), profile);
+
+    // Formats the decoder is not configured with are ignored
+    let mut track1_only = AdaptiveDecoder::new([Format::Track1, Format::Track1Inverted]);
+    let unmatched = track1_only
+        .import_profile(r#"{"swipes": 5, "formats": [{"format": "Track2Inverted", "successes": 4}, {"format": "Track1Inverted", "successes": 1}]}"#)
+        .unwrap();
+    assert_eq!(unmatched, ["Track2Inverted"]);
+    assert_eq!(
+        track1_only.formats(),
+        [Format::Track1Inverted, Format::Track1]
+    );
+}
+
+/// Test that a malformed profile is rejected without changing the decoder
+#[test]
+fn test_adaptive_invalid_profile() {
+    let mut decoder = AdaptiveDecoder::new(Format::FINANCIAL);
+    decoder
+        .decode(BitStream::new(&CARD, CARD_BITS).unwrap())
+        .unwrap();
+    let profile = decoder.export_profile();
+
+    for invalid in [
+        "not json",
+        r#"{"formats": []}"#,
+        r#"{"swipes": 1}"#,
+        r#"{"swipes": 1, "formats": [{"format": "Track2"}]}"#,
+    ] {
+        assert!(
+            matches!(
+                decoder.import_profile(invalid),
+                Err(DecoderError::InvalidProfile { .. })
+            ),
+            "{} should be rejected",
+            invalid
+        );
+    }
+    assert_eq!(decoder.export_profile(), profile);
+}
+
+/// A user decoder that always returns the same data, or always fails
+struct Constant(Option<&'static str>);
+
+/// Every `Constant` looks the same when debugged
+impl fmt::Debug for Constant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Constant")
+    }
+}
+
+impl TrackDecoder for Constant {
+    fn decode(
+        &self,
+        _stream: &BitStream,
+        _policy: &DecodePolicy,
+    ) -> Result<DecodedTrack, DecoderError> {
+        let data = self.0.ok_or(DecoderError::InvalidStartSentinel)?;
+        Ok(DecodedTrack::new(data, DecodeMetadata::default()))
+    }
+}
+
+/// Test that user formats that debug alike keep their own counts
+#[test]
+fn test_adaptive_profile_user_formats() {
+    let formats = vec![
+        Format::user(Constant(None)),
+        Format::user(Constant(Some("1"))),
+    ];
+    let mut decoder = AdaptiveDecoder::new(formats.clone());
+    decoder
+        .decode(BitStream::new(&CARD, CARD_BITS).unwrap())
+        .unwrap();
+    assert_eq!(decoder.formats()[0], formats[1]);
+
+    let profile = decoder.export_profile();
+    let json: serde_json::Value = serde_json::from_str(&profile).unwrap();
+    assert_eq!(json["formats"][0]["format"], "User#1");
+    assert_eq!(json["formats"][1]["format"], "User#0");
+
+    let mut restarted = AdaptiveDecoder::new(formats.clone());
+    assert!(restarted.import_profile(&profile).unwrap().is_empty());
+    assert_eq!(restarted.formats(), decoder.formats());
+    assert_eq!(restarted.successes(&formats[1]), 1);
+    assert_eq!(restarted.successes(&formats[0]), 0);
+}
+
+/// Test that a format listed twice keeps a count for each entry
+#[test]
+fn test_adaptive_profile_duplicate_formats() {
+    let formats = [Format::Track2Inverted, Format::Track2Inverted];
+    let mut decoder = AdaptiveDecoder::new(formats.clone());
+    decoder
+        .decode(BitStream::new(&CARD, CARD_BITS).unwrap())
+        .unwrap();
+
+    let profile = decoder.export_profile();
+    let json: serde_json::Value = serde_json::from_str(&profile).unwrap();
+    assert_eq!(json["formats"][0]["format"], "Track2Inverted");
+    assert_eq!(json["formats"][0]["successes"], 1);
+    assert_eq!(json["formats"][1]["format"], "Track2Inverted#1");
+    assert_eq!(json["formats"][1]["successes"], 0);
+
+    let mut restarted = AdaptiveDecoder::new(formats.clone());
+    assert!(restarted.import_profile(&profile).unwrap().is_empty());
+    assert_eq!(restarted.export_profile(), profile);
+
+    // The later entry can hold the higher count
+    let mut restarted = AdaptiveDecoder::new(formats);
+    let profile = r#"{"swipes": 2, "formats": [{"format": "Track2Inverted", "successes": 0}, {"format": "Track2Inverted#1", "successes": 2}]}"#;
+    assert!(restarted.import_profile(profile).unwrap().is_empty());
+    let json: serde_json::Value = serde_json::from_str(&restarted.export_profile()).unwrap();
+    assert_eq!(json["formats"][0]["format"], "Track2Inverted#1");
+    assert_eq!(json["formats"][0]["successes"], 2);
+    assert_eq!(json["formats"][1]["format"], "Track2Inverted");
+    assert_eq!(json["formats"][1]["successes"], 0);
+}
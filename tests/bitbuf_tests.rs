@@ -0,0 +1,95 @@
+use magstripe_rs::{BitBuf, BitStream, BitStreamError};
+
+/// Test building a buffer bit by bit
+#[test]
+fn test_bitbuf_push_and_pop() {
+    let mut bits = BitBuf::new();
+    assert!(bits.is_empty());
+    for bit in [true, true, false, true, false, true, true, false, true] {
+        bits.push(bit);
+    }
+    assert_eq!(bits.len(), 9);
+    assert_eq!(bits.as_bytes(), [0b11010110, 0b10000000]);
+    assert_eq!(bits.get(3), Some(true));
+    assert_eq!(bits.get(9), None);
+
+    assert_eq!(bits.pop(), Some(true));
+    assert_eq!(bits.pop(), Some(false));
+    assert_eq!(bits.as_bytes(), [0b11010110]);
+    bits.set(0, false);
+    assert_eq!(bits.to_string(), "0101011");
+
+    let mut empty = BitBuf::new();
+    assert_eq!(empty.pop(), None);
+}
+
+/// Test inserting and deleting single bits
+#[test]
+fn test_bitbuf_insert_and_remove() {
+    let mut bits: BitBuf = "11010110 1".parse().unwrap();
+    bits.insert(0, false);
+    bits.insert(5, true);
+    bits.insert(bits.len(), false);
+    assert_eq!(bits.to_string(), "011011011010");
+
+    assert!(bits.remove(5));
+    assert!(!bits.remove(0));
+    assert_eq!(bits.to_string(), "1101011010");
+}
+
+/// Test slicing, reversing, inverting and concatenating
+#[test]
+fn test_bitbuf_transformations() {
+    let bits: BitBuf = "1101_0110_10".parse().unwrap();
+    assert_eq!(bits.slice(2..7).to_string(), "01011");
+    assert!(bits.slice(4..4).is_empty());
+
+    let mut reversed = bits.clone();
+    reversed.reverse();
+    assert_eq!(reversed.to_string(), "0101101011");
+
+    let mut inverted = bits.clone();
+    inverted.invert();
+    assert_eq!(inverted.to_string(), "0010100101");
+    assert_eq!(inverted.as_bytes(), [0b00101001, 0b01000000]);
+    inverted.invert();
+    assert_eq!(inverted, bits);
+
+    let joined = BitBuf::concat([bits.as_stream(), reversed.as_stream()]);
+    assert_eq!(joined.len(), 20);
+    assert_eq!(joined.to_string(), "11010110100101101011");
+    assert_eq!(joined.slice(10..20), reversed);
+}
+
+/// Test conversions to and from streams, iterators and strings
+#[test]
+fn test_bitbuf_conversions() {
+    // Junk past the bit count is not copied
+    let data = [0b11010110, 0b10111111];
+    let stream = BitStream::new(&data, 10).unwrap();
+    let bits = BitBuf::from(&stream);
+    assert_eq!(bits.as_stream(), stream);
+    assert_eq!(bits.as_bytes(), [0b11010110, 0b10000000]);
+
+    let expected = [
+        true, true, false, true, false, true, true, false, true, false,
+    ];
+    let collected: BitBuf = expected.into_iter().collect();
+    assert_eq!(collected, bits);
+    assert_eq!(bits.iter().collect::<Vec<_>>(), expected);
+
+    let mut extended = bits.clone();
+    extended.extend([true, false]);
+    extended.extend_from_stream(&stream);
+    assert_eq!(extended.to_string(), format!("{}10{}", bits, bits));
+
+    assert_eq!(format!("{:?}", bits), "BitBuf(11010110:10)");
+    assert_eq!(
+        "01 0x1".parse::<BitBuf>(),
+        Err(BitStreamError::InvalidBit {
+            offset: 4,
+            value: b'x'
+        })
+    );
+    assert!("".parse::<BitBuf>().unwrap().is_empty());
+}
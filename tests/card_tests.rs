@@ -0,0 +1,108 @@
+mod common;
+
+use common::{encode_track, with_clock};
+use magstripe_rs::{BitBuf, BitStream, CardDecoder, DecoderError, Format, Track};
+
+const TRACK1: &str = "B4111111111111111^DOE/JANE^2512101";
+const TRACK2: &str = "4111111111111111=2512101";
+
+/// Test labelled streams, with one track failing on its own
+#[test]
+fn test_card_labelled_tracks() {
+    let track1: BitBuf = with_clock(&encode_track(Track::Track1, TRACK1), 20)
+        .parse()
+        .unwrap();
+    let track2: BitBuf = with_clock(&encode_track(Track::Track2, TRACK2), 20)
+        .parse()
+        .unwrap();
+    let noise = [0xA5u8; 8];
+
+    let card = CardDecoder::new().decode_tracks(
+        Some(track1.as_stream()),
+        Some(track2.as_stream()),
+        Some(BitStream::new(&noise, 64).unwrap()),
+    );
+    let track1 = card.track1.as_ref().unwrap().as_ref().unwrap();
+    assert_eq!(track1.data, TRACK1);
+    assert_eq!(track1.format, Format::Track1);
+    assert_eq!(
+        card.track(Track::Track2).unwrap().as_ref().unwrap().data,
+        TRACK2
+    );
+    assert!(matches!(
+        card.track3,
+        Some(Err(DecoderError::NoValidFormat { attempted: 2, .. }))
+    ));
+    let errors: Vec<Track> = card.errors().map(|(track, _)| track).collect();
+    assert_eq!(errors, [Track::Track3]);
+    assert_eq!(
+        card.assignments,
+        [
+            Some(Track::Track1),
+            Some(Track::Track2),
+            Some(Track::Track3)
+        ]
+    );
+
+    let card = CardDecoder::new().decode_tracks(None, None, None);
+    assert_eq!(card.track1, None);
+    assert!(card.assignments.is_empty());
+}
+
+/// Test that unlabelled streams are matched to their tracks
+#[test]
+fn test_card_unlabelled_tracks() {
+    let long_track3 = "9".repeat(60);
+    let track1: BitBuf = with_clock(&encode_track(Track::Track1, TRACK1), 20)
+        .parse()
+        .unwrap();
+    let track2: BitBuf = with_clock(&encode_track(Track::Track2, TRACK2), 20)
+        .parse()
+        .unwrap();
+    let track3: BitBuf = with_clock(&encode_track(Track::Track3, &long_track3), 20)
+        .parse()
+        .unwrap();
+
+    // Too long for Track 2, so read as Track 3 even in the first position
+    let streams = [track3.as_stream(), track2.as_stream(), track1.as_stream()];
+    let card = CardDecoder::new().decode_unlabelled(&streams);
+    assert_eq!(
+        card.assignments,
+        [
+            Some(Track::Track3),
+            Some(Track::Track2),
+            Some(Track::Track1)
+        ]
+    );
+    assert_eq!(card.track1.unwrap().unwrap().data, TRACK1);
+    assert_eq!(card.track2.unwrap().unwrap().data, TRACK2);
+    assert_eq!(card.track3.unwrap().unwrap().data, long_track3);
+
+    // A stream that decodes as nothing takes the track its position suggests
+    let noise = [0xA5u8; 8];
+    let streams = [track2.as_stream(), BitStream::new(&noise, 64).unwrap()];
+    let card = CardDecoder::new().decode_unlabelled(&streams);
+    assert_eq!(card.assignments, [Some(Track::Track2), Some(Track::Track1)]);
+    assert!(card.track1.unwrap().is_err());
+    assert_eq!(card.track2.unwrap().unwrap().data, TRACK2);
+    assert_eq!(card.track3, None);
+}
+
+/// Test overriding the formats of a track
+#[test]
+fn test_card_track_formats() {
+    let track2: BitBuf = with_clock(&encode_track(Track::Track2, TRACK2), 20)
+        .parse()
+        .unwrap();
+    let stream = track2.as_stream();
+
+    let decoder = CardDecoder::new().with_track_formats(Track::Track2, [Format::Track2Inverted]);
+    let card = decoder.decode_tracks(None, Some(stream), None);
+    let Some(Err(DecoderError::NoValidFormat { report, .. })) = card.track2 else {
+        panic!("expected NoValidFormat, got {:?}", card.track2);
+    };
+    assert!(report
+        .attempts
+        .iter()
+        .all(|attempt| attempt.format == Format::Track2Inverted));
+}
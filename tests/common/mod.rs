@@ -0,0 +1,152 @@
+//! Encoders shared by the integration tests, building the bits a reader
+//! would capture for a track.
+
+#![allow(dead_code)]
+
+use magstripe_rs::decoder::common::{insert_parity, strip_parity};
+use magstripe_rs::{Encoding, FormatSpec, Integrity, ParityType, Track};
+
+/// Real-world Track 2 inverted card that decodes to "0004048712"
+pub const CARD: [u8; 17] = [
+    255, 255, 255, 151, 222, 246, 253, 190, 141, 247, 7, 127, 255, 255, 255, 255, 192,
+];
+
+/// The number of bits captured in [`CARD`]
+pub const CARD_BITS: usize = 130;
+
+/// Encode character codes, parity included, as a binary string of
+/// `bits_per_char` bits each, LSB first
+pub fn codes_to_binary(codes: &[u8], bits_per_char: u8) -> String {
+    codes
+        .iter()
+        .flat_map(|code| {
+            (0..bits_per_char).map(move |i| if (code >> i) & 1 == 1 { '1' } else { '0' })
+        })
+        .collect()
+}
+
+/// The data bits of each character of `text`, which may include sentinels
+pub fn char_codes(track: Track, text: &str) -> Vec<u8> {
+    let base = match track {
+        Track::Track1 => 0x20,
+        Track::Track2 | Track::Track3 => 0x30,
+    };
+    text.bytes().map(|c| c - base).collect()
+}
+
+/// The data bits of `text` framed by the sentinels of the track and,
+/// optionally, followed by the LRC
+pub fn track_codes(track: Track, text: &str, with_lrc: bool) -> Vec<u8> {
+    let start = match track {
+        Track::Track1 => '%',
+        Track::Track2 | Track::Track3 => ';',
+    };
+    let mut codes = char_codes(track, &format!("{start}{text}?"));
+    if with_lrc {
+        codes.push(codes.iter().fold(0, |lrc, code| lrc ^ code));
+    }
+    codes
+}
+
+/// Encode data bits as a binary string, adding odd parity, LSB first
+pub fn encode_codes(codes: &[u8], track: Track) -> String {
+    encode_codes_with(codes, track, &Encoding::default())
+}
+
+/// Encode data bits as a binary string, adding the parity bit and applying
+/// the bit order and inversion of `encoding`
+pub fn encode_codes_with(codes: &[u8], track: Track, encoding: &Encoding) -> String {
+    encode_spec_codes(codes, &FormatSpec::from_track(track, encoding))
+}
+
+/// Encode data bits as characters of `spec`, adding the parity bit and
+/// applying the bit order and inversion of the spec
+pub fn encode_spec_codes(codes: &[u8], spec: &FormatSpec) -> String {
+    codes
+        .iter()
+        .map(|&code| encode_spec_char(code, spec, &spec.parity))
+        .collect()
+}
+
+/// Encode `text` as a whole track of `spec`: framed by its sentinels and
+/// followed by its check characters, mapping characters through the spec's
+/// own alphabet
+pub fn encode_spec(spec: &FormatSpec, text: &str) -> String {
+    let data_bits = spec.data_bits_per_char();
+    let data_of = |char_bits: u8| match spec.parity_bit() {
+        Some(position) => strip_parity(char_bits, position),
+        None => char_bits,
+    };
+
+    let mut codes: Vec<u8> = spec.start_sentinel.map(data_of).into_iter().collect();
+    codes.extend(
+        text.chars()
+            .map(|c| spec.alphabet.encode(c, spec.bits_per_char).unwrap()),
+    );
+    codes.extend(spec.end_sentinel.map(data_of));
+    let mut binary = encode_spec_codes(&codes, spec);
+
+    match spec.integrity {
+        Integrity::None => {}
+        Integrity::Lrc(lrc_spec) => {
+            let skip = usize::from(!lrc_spec.include_start_sentinel);
+            let lrc = codes[skip..].iter().fold(0, |lrc, code| lrc ^ code);
+            binary.push_str(&encode_spec_char(lrc, spec, &lrc_spec.parity));
+        }
+        Integrity::Crc(crc_spec) => {
+            let skip = usize::from(!crc_spec.include_start_sentinel);
+            let crc = crc_spec.checksum(&codes[skip..]).unwrap();
+            let check_chars = u32::from(crc_spec.width).div_ceil(u32::from(data_bits));
+            let padded =
+                u64::from(crc) << (check_chars * u32::from(data_bits) - u32::from(crc_spec.width));
+            let data_mask = ((1u16 << data_bits) - 1) as u8;
+            let check_codes: Vec<u8> = (0..check_chars)
+                .rev()
+                .map(|i| (padded >> (i * u32::from(data_bits))) as u8 & data_mask)
+                .collect();
+            binary.push_str(&encode_spec_codes(&check_codes, spec));
+        }
+    }
+    binary
+}
+
+/// Encode the data bits of one character of `spec`, with a parity bit of
+/// type `parity` in the spec's parity position
+fn encode_spec_char(code: u8, spec: &FormatSpec, parity: &ParityType) -> String {
+    let width = spec.bits_per_char;
+    let char_bits = match spec.parity_bit() {
+        Some(position) => insert_parity(code, position, parity),
+        None => code,
+    };
+    (0..width)
+        .map(|i| {
+            let bit = if spec.lsb_first {
+                (char_bits >> i) & 1
+            } else {
+                (char_bits >> (width - 1 - i)) & 1
+            };
+            if (bit == 1) != spec.inverted {
+                '1'
+            } else {
+                '0'
+            }
+        })
+        .collect()
+}
+
+/// Encode `text` as a whole track: framed by its sentinels, followed by the
+/// LRC, with odd parity and LSB first
+pub fn encode_track(track: Track, text: &str) -> String {
+    encode_track_with(track, text, &Encoding::default())
+}
+
+/// Encode `text` as a whole track with the given encoding options
+pub fn encode_track_with(track: Track, text: &str, encoding: &Encoding) -> String {
+    encode_codes_with(&track_codes(track, text, true), track, encoding)
+}
+
+/// Surround a binary string with `clock_bits` clock zeros on either side
+pub fn with_clock(binary: &str, clock_bits: usize) -> String {
+    let clock = "0".repeat(clock_bits);
+    format!("{clock}{binary}{clock}")
+}
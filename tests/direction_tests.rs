@@ -0,0 +1,61 @@
+#![allow(clippy::uninlined_format_args)]
+
+mod common;
+
+use common::{CARD, CARD_BITS};
+use magstripe_rs::{BitBuf, BitStream, Decoder, DecoderError, Format, SwipeDirection};
+
+/// A card read in the recorded order reports a forward swipe
+#[test]
+fn test_forward_swipe_direction() {
+    let stream = BitStream::new(&CARD, CARD_BITS).unwrap();
+    let decoder = Decoder::new(&[Format::Track2Inverted]);
+
+    let output = decoder.decode(stream).unwrap();
+    assert_eq!(output.data, "0004048712");
+    assert_eq!(output.direction, SwipeDirection::Forward);
+}
+
+/// The same card swiped backwards decodes to the same data
+#[test]
+fn test_reverse_swipe_direction() {
+    let mut reversed = BitBuf::from(BitStream::new(&CARD, CARD_BITS).unwrap());
+    reversed.reverse();
+    let stream = reversed.as_stream();
+
+    let formats = vec![Format::Track2, Format::Track2Inverted, Format::Track1];
+    let decoder = Decoder::new(&formats);
+
+    let output = decoder.decode(stream).unwrap();
+    assert_eq!(output.data, "0004048712");
+    assert!(matches!(output.format, Format::Track2Inverted));
+    assert_eq!(output.direction, SwipeDirection::Reverse);
+}
+
+/// Reverse decoding is also used by the free function API
+#[test]
+fn test_reverse_swipe_decode_with_formats() {
+    let mut reversed = BitBuf::from(BitStream::new(&CARD, CARD_BITS).unwrap());
+    reversed.reverse();
+    let stream = reversed.as_stream();
+
+    let output =
+        magstripe_rs::decoder::decode_with_formats(&[Format::Track2Inverted], stream).unwrap();
+    assert_eq!(output.data, "0004048712");
+    assert_eq!(output.direction, SwipeDirection::Reverse);
+}
+
+/// Disabling reverse decoding rejects backwards swipes
+#[test]
+fn test_reverse_swipe_disabled() {
+    let mut reversed = BitBuf::from(BitStream::new(&CARD, CARD_BITS).unwrap());
+    reversed.reverse();
+    let stream = reversed.as_stream();
+
+    let decoder = Decoder::new(&[Format::Track2Inverted]).with_reverse(false);
+
+    match decoder.decode(stream) {
+        Err(DecoderError::NoValidFormat { attempted, .. }) => assert_eq!(attempted, 1),
+        other => panic!("Backwards swipe should not decode: {:?}", other),
+    }
+}
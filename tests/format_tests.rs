@@ -1,32 +1,12 @@
 #![allow(clippy::uninlined_format_args)]
 
-use magstripe_rs::{BitStream, Decoder, Format};
+mod common;
 
-/// Helper function to convert a binary string to bytes
-fn binary_string_to_bytes(binary: &str) -> (Vec<u8>, usize) {
-    let bits: Vec<u8> = binary
-        .chars()
-        .filter(|c| *c == '0' || *c == '1')
-        .map(|c| if c == '1' { 1 } else { 0 })
-        .collect();
-
-    let bit_count = bits.len();
-    let mut bytes = Vec::new();
-    let mut current_byte = 0u8;
-
-    for (i, &bit) in bits.iter().enumerate() {
-        let bit_in_byte = i % 8;
-        if bit == 1 {
-            current_byte |= 1 << (7 - bit_in_byte);
-        }
-        if bit_in_byte == 7 || i == bits.len() - 1 {
-            bytes.push(current_byte);
-            current_byte = 0;
-        }
-    }
-
-    (bytes, bit_count)
-}
+use common::{encode_spec, encode_track_with, with_clock, CARD, CARD_BITS};
+use magstripe_rs::{
+    Alphabet, BitBuf, BitOrder, BitStream, CrcSpec, Decoder, DecoderError, Encoding, Format,
+    FormatSpec, Integrity, LrcSpec, OwnedDecoder, ParityType, SwipeDirection, Track,
+};
 
 /// Test Track2 inverted format with real-world data
 #[test]
@@ -77,8 +57,8 @@ fn test_track2_simple_pattern() {
         "11111111"
     );
 
-    let (bytes, bit_count) = binary_string_to_bytes(binary_data);
-    let stream = BitStream::new(&bytes, bit_count).unwrap();
+    let bits: BitBuf = binary_data.parse().unwrap();
+    let stream = bits.as_stream();
     let decoder = Decoder::new(&[Format::Track2]);
 
     match decoder.decode(stream) {
@@ -104,7 +84,8 @@ fn test_track1_formats() {
     let decoder = Decoder::new(&formats);
 
     // We expect this to fail, but it should attempt both formats
-    if let Err(magstripe_rs::DecoderError::NoValidFormat { attempted }) = decoder.decode(stream) {
+    if let Err(magstripe_rs::DecoderError::NoValidFormat { attempted, .. }) = decoder.decode(stream)
+    {
         assert_eq!(attempted, 2);
     }
     // If it somehow succeeds or fails differently, that's fine too
@@ -120,7 +101,8 @@ fn test_track3_format() {
     let decoder = Decoder::new(&[Format::Track3]);
 
     // We expect this to fail with random data
-    if let Err(magstripe_rs::DecoderError::NoValidFormat { attempted }) = decoder.decode(stream) {
+    if let Err(magstripe_rs::DecoderError::NoValidFormat { attempted, .. }) = decoder.decode(stream)
+    {
         assert_eq!(attempted, 1);
     }
     // If it somehow succeeds or fails differently, that's fine too
@@ -201,3 +183,526 @@ fn test_alternating_bits() {
         }
     }
 }
+
+/// Test that the named variants are presets over the encoding options
+#[test]
+fn test_named_variants_are_encoding_presets() {
+    let encoding = Encoding::default().with_inverted(true);
+    assert_eq!(
+        Format::Track2Inverted.encoding(),
+        Some((Track::Track2, encoding))
+    );
+
+    let formats = vec![Format::Encoded(Track::Track2, encoding)];
+    let stream = BitStream::new(&CARD, CARD_BITS).unwrap();
+    let output = Decoder::new(&formats).decode(stream).unwrap();
+    assert_eq!(output.data, "0004048712");
+}
+
+/// Test a combination of options that has no named variant
+#[test]
+fn test_combined_encoding_options() {
+    let encoding = Encoding::default()
+        .with_inverted(true)
+        .with_bit_order(BitOrder::MsbFirst)
+        .with_parity(ParityType::Even);
+
+    let clock = if encoding.inverted { "1" } else { "0" };
+    let binary = format!(
+        "{}{}{}",
+        clock.repeat(23),
+        encode_track_with(Track::Track2, "4929001234", &encoding),
+        clock.repeat(17)
+    );
+    let bits: BitBuf = binary.parse().unwrap();
+
+    // None of the single-option variants can read it
+    let presets = vec![
+        Format::Track2,
+        Format::Track2Inverted,
+        Format::Track2MSB,
+        Format::Track2EvenParity,
+    ];
+    let stream = bits.as_stream();
+    assert!(Decoder::new(&presets)
+        .with_reverse(false)
+        .decode(stream)
+        .is_err());
+
+    let formats = vec![Format::Encoded(Track::Track2, encoding)];
+    let stream = bits.as_stream();
+    let output = Decoder::new(&formats).decode(stream).unwrap();
+    assert_eq!(output.data, "4929001234");
+}
+
+/// Test that encoding options apply to Track 1 as well
+#[test]
+fn test_track1_encoding_options() {
+    // %A1? with even parity, MSB-first
+    let encoding = Encoding::default()
+        .with_bit_order(BitOrder::MsbFirst)
+        .with_parity(ParityType::Even);
+    let binary = with_clock(&encode_track_with(Track::Track1, "A1", &encoding), 10);
+    let bits: BitBuf = binary.parse().unwrap();
+    let stream = bits.as_stream();
+
+    let decoded = magstripe_rs::decoder::decode_track(&stream, Track::Track1, &encoding);
+    assert_eq!(decoded, Ok("A1".to_string()));
+}
+
+/// Test that built-in formats are presets over FormatSpec
+#[test]
+fn test_builtin_formats_are_spec_presets() {
+    assert_eq!(*Format::Track1.spec().unwrap(), FormatSpec::track1());
+    assert_eq!(*Format::Track2.spec().unwrap(), FormatSpec::track2());
+    assert_eq!(*Format::Track3.spec().unwrap(), FormatSpec::track3());
+
+    // Cloning a preset and changing one property behaves like the variant
+    let inverted = FormatSpec::track2()
+        .to_builder()
+        .inverted(true)
+        .build()
+        .unwrap();
+    assert_eq!(*Format::Track2Inverted.spec().unwrap(), inverted);
+
+    let stream = BitStream::new(&CARD, CARD_BITS).unwrap();
+    let formats = vec![Format::Custom(inverted)];
+    let output = Decoder::new(&formats).decode(stream).unwrap();
+    assert_eq!(output.data, "0004048712");
+}
+
+/// Test that custom specs verify the LRC like the built-in formats
+#[test]
+fn test_custom_spec_checks_lrc() {
+    let encoding = Encoding::default();
+    let mut encoded = encode_track_with(Track::Track2, "4929001234", &encoding);
+    // Corrupt the LRC while keeping its parity valid
+    let lrc_start = encoded.len() - 5;
+    let lrc: String = encoded[lrc_start..lrc_start + 2]
+        .chars()
+        .map(|c| if c == '1' { '0' } else { '1' })
+        .collect();
+    encoded.replace_range(lrc_start..lrc_start + 2, &lrc);
+
+    let binary = format!("{}{}{}", "0".repeat(12), encoded, "0".repeat(12));
+    let bits: BitBuf = binary.parse().unwrap();
+    let stream = bits.as_stream();
+
+    let result = magstripe_rs::decoder::decode_spec(&stream, &FormatSpec::track2());
+    assert_eq!(result, Err(DecoderError::LrcCheckFailed));
+
+    let without_lrc = FormatSpec::track2()
+        .to_builder()
+        .integrity(Integrity::None)
+        .build()
+        .unwrap();
+    let result = magstripe_rs::decoder::decode_spec(&stream, &without_lrc);
+    assert_eq!(result, Ok("4929001234".to_string()));
+}
+
+/// Test that the builder rejects inconsistent specs
+#[test]
+fn test_format_spec_builder_validation() {
+    let track2 = FormatSpec::track2();
+    let is_invalid = |result: Result<FormatSpec, DecoderError>| {
+        matches!(result, Err(DecoderError::InvalidCustomFormat { .. }))
+    };
+
+    // Sentinels wider than the character
+    assert!(is_invalid(track2.to_builder().bits_per_char(4).build()));
+    // Sentinel with the wrong parity
+    assert!(is_invalid(
+        track2.to_builder().start_sentinel(0b11011).build()
+    ));
+    assert!(is_invalid(
+        track2.to_builder().parity(ParityType::Even).build()
+    ));
+    // Parity bit outside the character
+    assert!(is_invalid(track2.to_builder().parity_position(5).build()));
+    // Width out of range
+    assert!(is_invalid(FormatSpec::builder(9).build()));
+    // An LRC needs an end sentinel to follow
+    assert!(is_invalid(
+        FormatSpec::builder(5).integrity(Integrity::ISO_LRC).build()
+    ));
+
+    // Specs that bypass the builder fail the same way when decoding
+    let data = vec![0u8; 8];
+    let stream = BitStream::new(&data, 64).unwrap();
+    let spec = FormatSpec {
+        bits_per_char: 4,
+        ..FormatSpec::track2()
+    };
+    assert!(matches!(
+        magstripe_rs::decoder::decode_spec(&stream, &spec),
+        Err(DecoderError::InvalidCustomFormat { .. })
+    ));
+}
+
+/// Test a 6-bit format with a vendor-specific character table
+#[test]
+fn test_custom_alphabet_table() {
+    // 5 data bits and odd parity; the last two table entries frame the data
+    let spec = FormatSpec::builder(6)
+        .start_sentinel(0b111110)
+        .end_sentinel(0b011111)
+        .parity(ParityType::Odd)
+        .integrity(Integrity::ISO_LRC)
+        .alphabet(Alphabet::Table(
+            "0123456789ABCDEFGHJKLMNPRSTUVW%?".chars().collect(),
+        ))
+        .build()
+        .unwrap();
+
+    let binary = format!(
+        "{}{}{}",
+        "0".repeat(15),
+        encode_spec(&spec, "C4MPUS7H9"),
+        "0".repeat(15)
+    );
+    let bits: BitBuf = binary.parse().unwrap();
+    let stream = bits.as_stream();
+
+    let formats = vec![Format::Custom(spec)];
+    let output = Decoder::new(&formats).decode(stream).unwrap();
+    assert_eq!(output.data, "C4MPUS7H9");
+}
+
+/// Test an offset alphabet restricted to a range of data values
+#[test]
+fn test_custom_alphabet_offset() {
+    // 5-bit characters without parity, A-Z at data values 0-25
+    let spec = FormatSpec::builder(5)
+        .start_sentinel(0b11110)
+        .end_sentinel(0b11111)
+        .alphabet(Alphabet::Offset {
+            base: b'A',
+            range: 0..=25,
+        })
+        .build()
+        .unwrap();
+
+    let binary = format!(
+        "{}{}{}",
+        "0".repeat(8),
+        encode_spec(&spec, "PARKING"),
+        "0".repeat(8)
+    );
+    let bits: BitBuf = binary.parse().unwrap();
+    let stream = bits.as_stream();
+    let decoded = magstripe_rs::decoder::decode_spec(&stream, &spec);
+    assert_eq!(decoded, Ok("PARKING".to_string()));
+
+    // Data value 27 lies outside the range of the alphabet
+    let binary = format!("{}{}{}", "01111", "11011", "11111");
+    let bits: BitBuf = binary.parse().unwrap();
+    let stream = bits.as_stream();
+    let decoded = magstripe_rs::decoder::decode_spec(&stream, &spec);
+    assert_eq!(
+        decoded,
+        Err(DecoderError::InvalidCharacter {
+            position: 1,
+            character: 27,
+        })
+    );
+}
+
+/// Test that alphabets map characters the same way in both directions
+#[test]
+fn test_alphabet_round_trip() {
+    let alphabets = [
+        (Alphabet::Standard, 5),
+        (Alphabet::Standard, 7),
+        (Alphabet::track1(), 7),
+        (Alphabet::track2(), 5),
+        (Alphabet::Table("0123456789ABCDEF".chars().collect()), 5),
+    ];
+
+    for (alphabet, width) in alphabets {
+        for data_bits in 0..16 {
+            let character = alphabet.decode(data_bits, width).unwrap();
+            assert_eq!(alphabet.encode(character, width), Some(data_bits));
+        }
+    }
+
+    assert_eq!(Alphabet::track2().decode(16, 5), None);
+    assert_eq!(Alphabet::track1().encode('a', 7), None);
+}
+
+/// Test the CRC presets against their published check values
+#[test]
+fn test_crc_check_values() {
+    assert_eq!(CrcSpec::CRC8.checksum(b"123456789"), Ok(0xF4));
+    assert_eq!(CrcSpec::CRC16_CCITT.checksum(b"123456789"), Ok(0x29B1));
+    assert_eq!(CrcSpec::CRC16_ARC.checksum(b"123456789"), Ok(0xBB3D));
+}
+
+/// Test that a CRC width outside 1 to 32 bits is rejected rather than
+/// computed
+#[test]
+fn test_crc_invalid_width() {
+    for width in [0, 33, 64] {
+        let crc = CrcSpec {
+            width,
+            poly: 0x07,
+            ..CrcSpec::CRC8
+        };
+        assert!(
+            matches!(
+                crc.checksum(b"123456789"),
+                Err(DecoderError::InvalidCustomFormat { .. })
+            ),
+            "width {}",
+            width
+        );
+    }
+
+    let crc = CrcSpec {
+        width: 32,
+        poly: 0x04C1_1DB7,
+        init: 0xFFFF_FFFF,
+        reflect: true,
+        xor_out: 0xFFFF_FFFF,
+        include_start_sentinel: true,
+    };
+    assert_eq!(crc.checksum(b"123456789"), Ok(0xCBF4_3926));
+}
+
+/// Test an 8-bit badge format framed by STX/ETX with a CRC-16 trailer
+#[test]
+fn test_custom_spec_checks_crc() {
+    let spec = FormatSpec::builder(8)
+        .start_sentinel(0x02)
+        .end_sentinel(0x03)
+        .integrity(Integrity::Crc(CrcSpec::CRC16_CCITT))
+        .alphabet(Alphabet::Offset {
+            base: 0x00,
+            range: 0x00..=0xFF,
+        })
+        .build()
+        .unwrap();
+
+    let encoded = encode_spec(&spec, "BADGE-0042");
+    let binary = format!("{}{}{}", "0".repeat(12), encoded, "0".repeat(12));
+    let bits: BitBuf = binary.parse().unwrap();
+    let stream = bits.as_stream();
+    assert_eq!(
+        magstripe_rs::decoder::decode_spec(&stream, &spec).unwrap(),
+        "BADGE-0042"
+    );
+
+    // Flip a bit in the last CRC character
+    let mut corrupted: Vec<char> = binary.chars().collect();
+    let idx = 12 + encoded.len() - 1;
+    corrupted[idx] = if corrupted[idx] == '1' { '0' } else { '1' };
+    let bits: BitBuf = corrupted.into_iter().collect::<String>().parse().unwrap();
+    let stream = bits.as_stream();
+    let expected = spec.integrity;
+    let Integrity::Crc(crc) = expected else {
+        unreachable!()
+    };
+    let mut covered = vec![0x02];
+    covered.extend_from_slice(b"BADGE-0042");
+    covered.push(0x03);
+    assert_eq!(
+        magstripe_rs::decoder::decode_spec(&stream, &spec),
+        Err(DecoderError::ChecksumMismatch {
+            expected: crc.checksum(&covered).unwrap(),
+            actual: crc.checksum(&covered).unwrap() ^ 0x0080,
+        })
+    );
+}
+
+/// Test a 5-bit format whose LRC has even parity and skips the start sentinel
+#[test]
+fn test_custom_lrc_options() {
+    let lrc = LrcSpec {
+        parity: ParityType::Even,
+        include_start_sentinel: false,
+    };
+    let spec = FormatSpec::track2()
+        .to_builder()
+        .integrity(Integrity::Lrc(lrc))
+        .build()
+        .unwrap();
+
+    let binary = format!(
+        "{}{}{}",
+        "0".repeat(10),
+        encode_spec(&spec, "8675309"),
+        "0".repeat(10)
+    );
+    let bits: BitBuf = binary.parse().unwrap();
+    let stream = bits.as_stream();
+    assert_eq!(
+        magstripe_rs::decoder::decode_spec(&stream, &spec).unwrap(),
+        "8675309"
+    );
+
+    // The ISO LRC of the same track does not match
+    let iso = FormatSpec::track2();
+    assert_eq!(
+        magstripe_rs::decoder::decode_spec(&stream, &iso),
+        Err(DecoderError::LrcCheckFailed)
+    );
+
+    // LRC parity needs a parity bit to live in
+    assert!(matches!(
+        FormatSpec::builder(4)
+            .start_sentinel(0b1011)
+            .end_sentinel(0b1111)
+            .integrity(Integrity::Lrc(lrc))
+            .build(),
+        Err(DecoderError::InvalidCustomFormat { .. })
+    ));
+}
+
+/// Test that 8-bit payloads are available as bytes, and as text only when
+/// they are printable
+#[test]
+fn test_binary_payload() {
+    let spec = FormatSpec::builder(8)
+        .start_sentinel(0x02)
+        .end_sentinel(0x03)
+        .integrity(Integrity::Crc(CrcSpec::CRC8))
+        .build()
+        .unwrap();
+    let formats = vec![Format::Custom(spec.clone())];
+    let decoder = Decoder::new(&formats).with_reverse(false);
+
+    let payload = [0x00, 0x9F, 0xFF, 0x41, 0x0A, 0xE9];
+    let text: String = payload.iter().map(|&b| char::from(b)).collect();
+    let bits: BitBuf = encode_spec(&spec, &text).parse().unwrap();
+    let stream = bits.as_stream();
+    let output = decoder.decode(stream).unwrap();
+    assert_eq!(output.bytes, payload);
+    assert_eq!(output.text(), None);
+
+    let bits: BitBuf = encode_spec(&spec, "ID 4711").parse().unwrap();
+    let stream = bits.as_stream();
+    let output = decoder.decode(stream).unwrap();
+    assert_eq!(output.bytes, b"ID 4711");
+    assert_eq!(output.text(), Some("ID 4711"));
+}
+
+/// Test that the failure of every attempted format is reported
+#[test]
+fn test_decode_report() {
+    // Corrupt the LRC of an otherwise valid Track 2
+    let mut binary = encode_track_with(Track::Track2, "4111111111111111", &Encoding::default());
+    let last = binary.len() - 5;
+    let flipped = if &binary[last..last + 1] == "1" {
+        "0"
+    } else {
+        "1"
+    };
+    binary.replace_range(last..last + 1, flipped);
+    let binary = format!("{}{}{}", "0".repeat(10), binary, "0".repeat(10));
+    let bits: BitBuf = binary.parse().unwrap();
+
+    let formats = vec![Format::Track2, Format::Track1];
+    let decoder = Decoder::new(&formats);
+    let stream = bits.as_stream();
+    let (result, report) = decoder.decode_with_report(stream);
+
+    // Both formats are attempted forwards, then in reverse
+    assert_eq!(report.attempts.len(), 4);
+    assert_eq!(report.attempts[0].format, Format::Track2);
+    assert_eq!(report.attempts[0].direction, SwipeDirection::Forward);
+    assert_eq!(report.attempts[0].error, DecoderError::LrcCheckFailed);
+    assert_eq!(report.attempts_for(&Format::Track1).count(), 2);
+    assert_eq!(
+        report.to_string().lines().next(),
+        Some("Track2 (Forward): LRC check failed")
+    );
+    assert_eq!(
+        result,
+        Err(DecoderError::NoValidFormat {
+            attempted: 2,
+            report
+        })
+    );
+
+    // On success, the report covers the formats that failed first
+    let formats = vec![Format::Track1, Format::Track2Inverted, Format::Track2];
+    let decoder = Decoder::new(&formats);
+    let binary = format!(
+        "{}{}{}",
+        "0".repeat(10),
+        encode_track_with(Track::Track2, "4111111111111111", &Encoding::default()),
+        "0".repeat(10)
+    );
+    let bits: BitBuf = binary.parse().unwrap();
+    let stream = bits.as_stream();
+    let (result, report) = decoder.decode_with_report(stream);
+    assert_eq!(result.unwrap().data, "4111111111111111");
+    let failed: Vec<&Format> = report.attempts.iter().map(|a| &a.format).collect();
+    assert_eq!(failed, [&Format::Track1, &Format::Track2Inverted]);
+
+    // A track with nothing between its sentinels says so
+    let formats = vec![Format::Track2];
+    let binary = format!(
+        "{}{}{}",
+        "0".repeat(10),
+        encode_track_with(Track::Track2, "", &Encoding::default()),
+        "0".repeat(10)
+    );
+    let bits: BitBuf = binary.parse().unwrap();
+    let (result, report) = Decoder::new(&formats)
+        .with_reverse(false)
+        .decode_with_report(bits.as_stream());
+    assert!(result.is_err());
+    assert_eq!(report.attempts[0].error, DecoderError::EmptyTrack);
+    assert_eq!(
+        report.to_string(),
+        "Track2 (Forward): Track holds no data characters"
+    );
+}
+
+/// Test that the all-variants preset lists every named format exactly once
+///
+/// The match below has no wildcard arm, so a new `Format` variant fails to
+/// compile here until it is numbered and counted in `seen`, and then fails
+/// the test until it is added to the preset.
+#[test]
+fn test_presets() {
+    let index = |format: &Format| match format {
+        Format::Track2 => 0,
+        Format::Track2Inverted => 1,
+        Format::Track2MSB => 2,
+        Format::Track2LSB => 3,
+        Format::Track2Raw => 4,
+        Format::Track2SwappedParity => 5,
+        Format::Track2EvenParity => 6,
+        Format::Track1 => 7,
+        Format::Track1Inverted => 8,
+        Format::Track3 => 9,
+        Format::Encoded(..) | Format::Custom(_) | Format::User(_) => {
+            panic!("{:?} is parametric", format)
+        }
+    };
+    let mut seen = [0; 10];
+    for format in Format::ALL_VARIANTS {
+        seen[index(format)] += 1;
+    }
+    assert_eq!(seen, [1; 10], "every named format must appear once");
+
+    // The other presets are subsets, and the loose raw format comes last
+    for preset in [Format::FINANCIAL, Format::ALL_ISO, Format::ID_CARDS] {
+        assert!(preset.iter().all(|f| Format::ALL_VARIANTS.contains(f)));
+    }
+    assert_eq!(Format::ALL_VARIANTS.last(), Some(&Format::Track2Raw));
+
+    let stream = BitStream::new(&CARD, CARD_BITS).unwrap();
+    let output = Decoder::new(Format::FINANCIAL)
+        .decode(stream.clone())
+        .unwrap();
+    assert_eq!(output.format, &Format::Track2Inverted);
+
+    // The default decoders use the financial preset, as the CLI does
+    assert_eq!(Decoder::default().decode(stream.clone()), Ok(output));
+    assert_eq!(
+        OwnedDecoder::default().formats().as_ref(),
+        Format::FINANCIAL
+    );
+}
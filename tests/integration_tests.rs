@@ -90,7 +90,7 @@ fn test_all_zeros() {
     let decoder = Decoder::new(&[Format::Track2, Format::Track2Inverted]);
 
     match decoder.decode(stream) {
-        Err(magstripe_rs::DecoderError::NoValidFormat { attempted }) => {
+        Err(magstripe_rs::DecoderError::NoValidFormat { attempted, .. }) => {
             assert_eq!(attempted, 2);
         }
         _ => panic!("Should fail to decode all zeros"),
@@ -106,7 +106,7 @@ fn test_all_ones() {
     let decoder = Decoder::new(&[Format::Track2, Format::Track2Inverted]);
 
     match decoder.decode(stream) {
-        Err(magstripe_rs::DecoderError::NoValidFormat { attempted }) => {
+        Err(magstripe_rs::DecoderError::NoValidFormat { attempted, .. }) => {
             assert_eq!(attempted, 2);
         }
         _ => panic!("Should fail to decode all ones"),
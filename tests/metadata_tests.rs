@@ -0,0 +1,126 @@
+#![allow(clippy::uninlined_format_args)]
+
+mod common;
+
+use common::{codes_to_binary, CARD, CARD_BITS};
+use magstripe_rs::{BitBuf, BitStream, CharacterInfo, CharacterKind, CheckStatus, Decoder, Format};
+
+/// `;123?` and its LRC, with odd parity in bit 4
+const TRACK2_123: [u8; 6] = [0b01011, 0b00001, 0b00010, 0b10011, 0b11111, 0b00100];
+
+/// Test that the offsets and codes of every character are reported
+#[test]
+fn test_character_metadata() {
+    let binary = format!(
+        "{}{}{}",
+        "0".repeat(12),
+        codes_to_binary(&TRACK2_123, 5),
+        "0".repeat(9)
+    );
+    let bits: BitBuf = binary.parse().unwrap();
+    let stream = bits.as_stream();
+
+    let output = Decoder::new(&[Format::Track2]).decode(stream).unwrap();
+    assert_eq!(output.data, "123");
+
+    let metadata = &output.metadata;
+    assert_eq!(metadata.start_sentinel_offset, Some(12));
+    assert_eq!(metadata.end_sentinel_offset, Some(32));
+    assert_eq!(metadata.check_offset, Some(37));
+    assert!(metadata.integrity_present);
+    assert!(metadata.integrity_checked);
+    assert_eq!(metadata.leading_clock_bits, 12);
+    assert_eq!(metadata.trailing_clock_bits, 9);
+    assert_eq!(metadata.bit_range, 12..42);
+
+    let kinds = [
+        CharacterKind::StartSentinel,
+        CharacterKind::Data('1'),
+        CharacterKind::Data('2'),
+        CharacterKind::Data('3'),
+        CharacterKind::EndSentinel,
+        CharacterKind::Check,
+    ];
+    let expected: Vec<CharacterInfo> = TRACK2_123
+        .iter()
+        .zip(kinds)
+        .enumerate()
+        .map(|(i, (&code, kind))| CharacterInfo {
+            offset: 12 + i * 5,
+            code,
+            kind,
+        })
+        .collect();
+    assert_eq!(metadata.characters, expected);
+}
+
+/// Test that an LRC cut off by the end of the stream is reported as missing
+#[test]
+fn test_missing_lrc_metadata() {
+    let binary = format!("{}{}", "0".repeat(7), codes_to_binary(&TRACK2_123[..5], 5));
+    let bits: BitBuf = binary.parse().unwrap();
+    let stream = bits.as_stream();
+
+    let output = Decoder::new(&[Format::Track2]).decode(stream).unwrap();
+    let metadata = &output.metadata;
+    assert_eq!(metadata.check_offset, None);
+    assert!(!metadata.integrity_present);
+    assert!(!metadata.integrity_checked);
+    assert_eq!(metadata.bit_range, 7..32);
+    assert_eq!(metadata.trailing_clock_bits, 0);
+    assert_eq!(metadata.characters.len(), 5);
+}
+
+/// Test that clock bits of inverted cards are counted as ones
+#[test]
+fn test_inverted_clock_bits() {
+    let stream = BitStream::new(&CARD, CARD_BITS).unwrap();
+
+    let output = Decoder::new(&[Format::Track2Inverted])
+        .decode(stream)
+        .unwrap();
+    let metadata = &output.metadata;
+    assert_eq!(metadata.start_sentinel_offset, Some(25));
+    assert_eq!(metadata.leading_clock_bits, 25);
+    assert_eq!(metadata.bit_range.start, 25);
+    assert_eq!(metadata.characters.len(), 13);
+    assert_eq!(metadata.bit_range.end + metadata.trailing_clock_bits, 130);
+}
+
+/// Test the quality figures of clean and clipped reads
+#[test]
+fn test_swipe_quality() {
+    let decoder_formats = [Format::Track2];
+    let decoder = Decoder::new(&decoder_formats).with_reverse(false);
+
+    // Plenty of clock bits and a verified LRC
+    let binary = format!(
+        "{}{}{}",
+        "0".repeat(12),
+        codes_to_binary(&TRACK2_123, 5),
+        "0".repeat(9)
+    );
+    let bits: BitBuf = binary.parse().unwrap();
+    let quality = decoder.decode(bits.as_stream()).unwrap().quality;
+    assert_eq!(quality.check, CheckStatus::Verified);
+    assert_eq!(quality.leading_clock_bits, 12);
+    assert_eq!(quality.trailing_clock_bits, 9);
+    assert_eq!(quality.parity_failures, 0);
+    assert_eq!(quality.sentinel_skew, 0);
+    assert_eq!(quality.confidence, 1.0);
+
+    // Noise before the clock bits, and the LRC cut off
+    let binary = format!(
+        "{}{}{}",
+        "0111",
+        "0".repeat(12),
+        codes_to_binary(&TRACK2_123[..5], 5)
+    );
+    let bits: BitBuf = binary.parse().unwrap();
+    let clipped = decoder.decode(bits.as_stream()).unwrap().quality;
+    assert_eq!(clipped.check, CheckStatus::Missing);
+    assert_eq!(clipped.sentinel_skew, 4);
+    assert_eq!(clipped.trailing_clock_bits, 0);
+    assert!(clipped.confidence < quality.confidence);
+    assert!(clipped.confidence > 0.0);
+}
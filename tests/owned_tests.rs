@@ -0,0 +1,101 @@
+mod common;
+
+use common::{CARD, CARD_BITS};
+use magstripe_rs::{
+    BitBuf, BitStream, Decoder, DecoderError, Format, FormatSpec, OwnedDecodeCandidates,
+    OwnedDecoder, OwnedDecoderBuilder, OwnedDecoderOutput, SwipeDirection,
+};
+use std::sync::Arc;
+
+/// Fails to compile unless `T` can be shared between threads
+fn assert_send_sync<T: Send + Sync + 'static>() {}
+
+/// Test that the owned types can be shared between threads
+#[test]
+fn test_owned_types_are_send_sync() {
+    assert_send_sync::<OwnedDecoder>();
+    assert_send_sync::<OwnedDecoderBuilder>();
+    assert_send_sync::<OwnedDecoderOutput>();
+    assert_send_sync::<OwnedDecodeCandidates>();
+    assert_send_sync::<DecoderError>();
+}
+
+/// Test that an owned decoder decodes like the borrowing one
+#[test]
+fn test_owned_decoder_matches_decoder() {
+    let formats = vec![Format::Track2, Format::Track2Inverted];
+    let stream = BitStream::new(&CARD, CARD_BITS).unwrap();
+    let borrowed = Decoder::new(&formats).decode(stream.clone()).unwrap();
+
+    let decoder = OwnedDecoder::new(formats.clone());
+    let owned = decoder.decode(stream).unwrap();
+    assert_eq!(owned, borrowed.clone().into_owned());
+    assert_eq!(owned.format, Format::Track2Inverted);
+    assert_eq!(owned.text(), Some("0004048712"));
+    assert_eq!(OwnedDecoderOutput::from(borrowed), owned);
+}
+
+/// Test that ranked candidates outlive the owned decoder that made them
+#[test]
+fn test_owned_decode_all() {
+    let formats = vec![Format::Track2, Format::Track2Inverted, Format::Track2Raw];
+    let stream = BitStream::new(&CARD, CARD_BITS).unwrap();
+    let borrowed = Decoder::new(&formats).decode_all(stream.clone()).unwrap();
+
+    let ranked = OwnedDecoder::new(formats.clone())
+        .decode_all(stream)
+        .unwrap();
+    assert_eq!(ranked, borrowed.clone().into_owned());
+    assert_eq!(ranked.best().output.format, Format::Track2Inverted);
+    assert_eq!(ranked.best().score, borrowed.best().score);
+    assert!(ranked.best().is_clean());
+}
+
+/// Test the builder options and a decoder shared by several threads
+#[test]
+fn test_owned_decoder_builder() {
+    let formats: Arc<[Format]> = Arc::from(vec![Format::Track2Inverted]);
+    let decoder = OwnedDecoder::builder()
+        .formats(formats.clone())
+        .reverse(false)
+        .build()
+        .unwrap();
+    assert!(Arc::ptr_eq(decoder.formats(), &formats));
+
+    let mut reversed = BitBuf::from(BitStream::new(&CARD, CARD_BITS).unwrap());
+    reversed.reverse();
+    assert!(decoder.decode(reversed.as_stream()).is_err());
+
+    let decoder = Arc::new(
+        OwnedDecoder::builder()
+            .formats(formats)
+            .reverse(true)
+            .build()
+            .unwrap(),
+    );
+    let handlers: Vec<_> = (0..4)
+        .map(|_| {
+            let decoder = Arc::clone(&decoder);
+            let reversed = reversed.clone();
+            std::thread::spawn(move || decoder.decode(reversed.as_stream()))
+        })
+        .collect();
+    for handler in handlers {
+        let output = handler.join().unwrap().unwrap();
+        assert_eq!(output.data, "0004048712");
+        assert_eq!(output.direction, SwipeDirection::Reverse);
+    }
+
+    assert_eq!(
+        OwnedDecoder::builder().formats(Vec::new()).build().err(),
+        Some(DecoderError::NoFormatsProvided)
+    );
+    let mut invalid = FormatSpec::track2();
+    invalid.bits_per_char = 0;
+    assert!(matches!(
+        OwnedDecoder::builder()
+            .formats([Format::Custom(invalid)])
+            .build(),
+        Err(DecoderError::InvalidCustomFormat { .. })
+    ));
+}
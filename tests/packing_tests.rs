@@ -0,0 +1,104 @@
+mod common;
+
+use common::{CARD, CARD_BITS};
+use magstripe_rs::{BitPacking, BitStream, BitStreamError, Decoder, Format};
+
+const LAYOUTS: [BitPacking; 5] = [
+    BitPacking::MsbFirst,
+    BitPacking::LsbFirst,
+    BitPacking::RightAligned,
+    BitPacking::BitPerByte,
+    BitPacking::Ascii,
+];
+
+/// Test the bytes each layout writes for a partly filled final byte
+#[test]
+fn test_packing_layouts() {
+    let data = [0b11010110, 0b10100000];
+    let stream = BitStream::new(&data, 12).unwrap();
+
+    assert_eq!(BitPacking::MsbFirst.pack(&stream), [0b11010110, 0b10100000]);
+    assert_eq!(BitPacking::LsbFirst.pack(&stream), [0b01101011, 0b00000101]);
+    assert_eq!(
+        BitPacking::RightAligned.pack(&stream),
+        [0b11010110, 0b00001010]
+    );
+    assert_eq!(
+        BitPacking::BitPerByte.pack(&stream),
+        [1, 1, 0, 1, 0, 1, 1, 0, 1, 0, 1, 0]
+    );
+    assert_eq!(BitPacking::Ascii.pack(&stream), b"110101101010");
+}
+
+/// Test that every layout round-trips through the left-aligned representation
+#[test]
+fn test_packing_round_trip() {
+    let stream = BitStream::new(&CARD, CARD_BITS).unwrap();
+    for layout in LAYOUTS {
+        for bit_count in [0, 1, 7, 8, 9, CARD_BITS] {
+            let stream = BitStream::new(stream.buffer(), bit_count).unwrap();
+            let packed = layout.pack(&stream);
+            assert_eq!(packed.len(), layout.required_bytes(bit_count));
+
+            let unpacked = layout.unpack(&packed, bit_count).unwrap();
+            assert_eq!(
+                BitStream::new(&unpacked, bit_count).unwrap(),
+                stream,
+                "{:?} with {} bits",
+                layout,
+                bit_count
+            );
+        }
+    }
+}
+
+/// Test decoding a card delivered least significant bit first
+#[test]
+fn test_packing_decode_lsb_first() {
+    let stream = BitStream::new(&CARD, CARD_BITS).unwrap();
+    let raw = BitPacking::LsbFirst.pack(&stream);
+    assert_ne!(raw, CARD);
+
+    let buffer = BitPacking::LsbFirst.unpack(&raw, CARD_BITS).unwrap();
+    let formats = [Format::Track2Inverted];
+    let output = Decoder::new(&formats)
+        .decode(BitStream::new(&buffer, CARD_BITS).unwrap())
+        .unwrap();
+    assert_eq!(output.data, "0004048712");
+}
+
+/// Test that malformed input is rejected
+#[test]
+fn test_packing_invalid_input() {
+    assert_eq!(
+        BitPacking::Ascii.unpack(b"0110", 5),
+        Err(BitStreamError::BufferTooSmall {
+            required_bytes: 5,
+            provided_bytes: 4
+        })
+    );
+    assert_eq!(
+        BitPacking::LsbFirst.unpack(&[0xFF], 9),
+        Err(BitStreamError::BufferTooSmall {
+            required_bytes: 2,
+            provided_bytes: 1
+        })
+    );
+    assert_eq!(
+        BitPacking::Ascii.unpack(b"01 10", 5),
+        Err(BitStreamError::InvalidBit {
+            offset: 2,
+            value: b' '
+        })
+    );
+    assert_eq!(
+        BitPacking::BitPerByte.unpack(&[0, 1, 0xFF], 3),
+        Err(BitStreamError::InvalidBit {
+            offset: 2,
+            value: 0xFF
+        })
+    );
+
+    // Bytes past the bit count are not read
+    assert_eq!(BitPacking::Ascii.unpack(b"01x", 2), Ok(vec![0b01000000]));
+}
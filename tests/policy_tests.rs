@@ -0,0 +1,302 @@
+#![allow(clippy::uninlined_format_args)]
+
+mod common;
+
+use common::{encode_codes, encode_track, track_codes};
+use magstripe_rs::decoder::decode_spec_with_policy;
+use magstripe_rs::{
+    BitBuf, CheckStatus, DecodePolicy, Decoder, DecoderError, Format, FormatSpec, Track,
+};
+
+/// Helper function to decode a binary string as standard Track 2
+fn decode(binary: &str, policy: &DecodePolicy) -> Result<String, DecoderError> {
+    let bits: BitBuf = binary.parse().unwrap();
+    let stream = bits.as_stream();
+    decode_spec_with_policy(&stream, &FormatSpec::track2(), policy)
+}
+
+/// Test that strict mode requires the LRC
+#[test]
+fn test_strict_requires_lrc() {
+    let binary = format!(
+        "{}{}",
+        "0".repeat(10),
+        encode_codes(&track_codes(Track::Track2, "1234", false), Track::Track2)
+    );
+
+    assert_eq!(
+        decode(&binary, &DecodePolicy::standard()),
+        Ok("1234".to_string())
+    );
+    assert_eq!(
+        decode(&binary, &DecodePolicy::strict()),
+        Err(DecoderError::MissingCheckCharacter)
+    );
+}
+
+/// Test that strict mode rejects anything but clock bits after the LRC
+#[test]
+fn test_strict_rejects_trailing_data() {
+    let track = encode_track(Track::Track2, "1234");
+    let binary = format!("{}{}{}{}", "0".repeat(10), track, "000101", "0".repeat(4));
+
+    assert_eq!(
+        decode(&binary, &DecodePolicy::standard()),
+        Ok("1234".to_string())
+    );
+    assert_eq!(
+        decode(&binary, &DecodePolicy::strict()),
+        Err(DecoderError::TrailingData {
+            offset: 10 + track.len() + 3
+        })
+    );
+
+    // Clock bits alone are fine
+    let binary = format!("{}{}{}", "0".repeat(10), track, "0".repeat(10));
+    assert_eq!(
+        decode(&binary, &DecodePolicy::strict()),
+        Ok("1234".to_string())
+    );
+}
+
+/// Test that the ISO length limit of Track 2 is enforced in strict mode
+#[test]
+fn test_strict_enforces_length_limits() {
+    // 37 digits fill Track 2 exactly, together with the sentinels and LRC
+    let longest = "0123456789".repeat(4)[..37].to_string();
+    let binary = format!(
+        "{}{}",
+        "0".repeat(10),
+        encode_track(Track::Track2, &longest)
+    );
+    assert_eq!(
+        decode(&binary, &DecodePolicy::strict()),
+        Ok(longest.clone())
+    );
+
+    let too_long = longest + "1";
+    let binary = format!(
+        "{}{}",
+        "0".repeat(10),
+        encode_track(Track::Track2, &too_long)
+    );
+    assert_eq!(
+        decode(&binary, &DecodePolicy::standard()),
+        Ok(too_long.clone())
+    );
+    assert_eq!(
+        decode(&binary, &DecodePolicy::strict()),
+        Err(DecoderError::TrackTooLong {
+            length: 41,
+            maximum: 40
+        })
+    );
+}
+
+/// Test that lenient mode accepts a track cut off before its end sentinel
+#[test]
+fn test_lenient_accepts_missing_end_sentinel() {
+    let mut codes = track_codes(Track::Track2, "4242", false);
+    codes.pop();
+    let binary = format!(
+        "{}{}{}",
+        "0".repeat(10),
+        encode_codes(&codes, Track::Track2),
+        "0".repeat(12)
+    );
+
+    // The clock bits after the data fail parity unless the track may end there
+    assert_eq!(
+        decode(&binary, &DecodePolicy::standard()),
+        Err(DecoderError::ParityError { position: 5 })
+    );
+    assert_eq!(
+        decode(&binary, &DecodePolicy::lenient()),
+        Ok("4242".to_string())
+    );
+
+    // The policy applies to every format tried by a decoder
+    let bits: BitBuf = binary.parse().unwrap();
+    let formats = vec![Format::Track2];
+    let decoder = Decoder::new(&formats).with_policy(DecodePolicy::lenient());
+    let output = decoder.decode(bits.as_stream()).unwrap();
+    assert_eq!(output.data, "4242");
+    assert_eq!(output.metadata.end_sentinel_offset, None);
+    assert_eq!(output.metadata.bit_range, 10..35);
+}
+
+/// Helper function to flip one bit of a binary string
+fn flip(binary: &str, index: usize) -> String {
+    binary
+        .char_indices()
+        .map(|(i, c)| match (i == index, c) {
+            (true, '1') => '0',
+            (true, _) => '1',
+            (false, c) => c,
+        })
+        .collect()
+}
+
+/// Test that bad characters are replaced by the placeholder and reported
+#[test]
+fn test_placeholder_for_bad_characters() {
+    let formats = vec![Format::Track2];
+    let policy = DecodePolicy::standard().with_placeholder('_');
+    let decoder = Decoder::new(&formats)
+        .with_reverse(false)
+        .with_policy(policy);
+    let binary = format!(
+        "{}{}{}",
+        "0".repeat(10),
+        encode_track(Track::Track2, "1234"),
+        "0".repeat(10)
+    );
+
+    // Flip the parity bit of the third digit: the LRC still matches
+    let damaged = flip(&binary, 10 + 3 * 5 + 4);
+    let bits: BitBuf = damaged.parse().unwrap();
+    let output = decoder.decode(bits.as_stream()).unwrap();
+    assert_eq!(output.data, "12_4");
+    assert_eq!(output.errors, [DecoderError::ParityError { position: 3 }]);
+    assert_eq!(output.quality.parity_failures, 1);
+    assert_eq!(output.quality.check, CheckStatus::Verified);
+
+    // Flip a data bit of the first digit: the LRC fails too
+    let damaged = flip(&binary, 10 + 5);
+    let bits: BitBuf = damaged.parse().unwrap();
+    let output = decoder.decode(bits.as_stream()).unwrap();
+    assert_eq!(output.data, "_234");
+    assert_eq!(
+        output.errors,
+        [
+            DecoderError::ParityError { position: 1 },
+            DecoderError::LrcCheckFailed
+        ]
+    );
+    assert_eq!(output.quality.check, CheckStatus::Failed);
+    assert!(!output.metadata.integrity_checked);
+
+    // Without a placeholder the first error is fatal
+    assert_eq!(
+        decode(&damaged, &DecodePolicy::standard()),
+        Err(DecoderError::ParityError { position: 1 })
+    );
+}
+
+/// Test partial decodes of Track 1 and custom formats in lenient mode
+#[test]
+fn test_lenient_partial_decode() {
+    // Track 1: '%', "AB12", '?' and the LRC, 6 data bits plus odd parity
+    let track1 = encode_track(Track::Track1, "AB12");
+    let binary = flip(&format!("{}{}", "0".repeat(9), track1), 9 + 2 * 7 + 1);
+
+    let formats = vec![Format::Track1];
+    let decoder = Decoder::new(&formats).with_policy(DecodePolicy::lenient());
+    let bits: BitBuf = binary.parse().unwrap();
+    let output = decoder.decode(bits.as_stream()).unwrap();
+    assert_eq!(output.data, "A\u{FFFD}12");
+    assert_eq!(output.errors.len(), 2);
+
+    // A custom 5-bit format without an LRC, with a character outside its alphabet
+    let spec = FormatSpec::track2()
+        .to_builder()
+        .integrity(magstripe_rs::Integrity::None)
+        .alphabet(magstripe_rs::Alphabet::Offset {
+            base: 0x30,
+            range: 0..=9,
+        })
+        .build()
+        .unwrap();
+    let mut codes = track_codes(Track::Track2, "1234", false);
+    codes[2] = 0x0C;
+    let binary = format!("{}{}", "0".repeat(10), encode_codes(&codes, Track::Track2));
+    let formats = vec![Format::Custom(spec)];
+    let policy = DecodePolicy::lenient().with_placeholder('#');
+    let decoder = Decoder::new(&formats).with_policy(policy);
+    let bits: BitBuf = binary.parse().unwrap();
+    let output = decoder.decode(bits.as_stream()).unwrap();
+    assert_eq!(output.data, "1#34");
+    assert_eq!(
+        output.errors,
+        [DecoderError::InvalidCharacter {
+            position: 2,
+            character: 0x0C
+        }]
+    );
+}
+
+/// Test that a single damaged character is rebuilt from the LRC
+#[test]
+fn test_single_character_correction() {
+    let binary = format!(
+        "{}{}{}",
+        "0".repeat(10),
+        encode_track(Track::Track2, "1234"),
+        "0".repeat(10)
+    );
+    let correcting = DecodePolicy::standard().with_correction(true);
+
+    // A flipped data bit in the third digit
+    let damaged = flip(&binary, 10 + 3 * 5 + 1);
+    assert_eq!(
+        decode(&damaged, &DecodePolicy::standard()),
+        Err(DecoderError::ParityError { position: 3 })
+    );
+    assert_eq!(decode(&damaged, &correcting), Ok("1234".to_string()));
+
+    let formats = vec![Format::Track2];
+    let decoder = Decoder::new(&formats).with_policy(correcting);
+    let bits: BitBuf = damaged.parse().unwrap();
+    let output = decoder.decode(bits.as_stream()).unwrap();
+    assert_eq!(output.metadata.corrected, [3]);
+    let clean: BitBuf = binary.parse().unwrap();
+    let clean = decoder.decode(clean.as_stream()).unwrap();
+    assert_eq!(output.metadata.characters, clean.metadata.characters);
+    assert!(output.errors.is_empty());
+    assert_eq!(output.quality.parity_failures, 1);
+    assert_eq!(output.quality.parity_recovered, 1);
+    assert_eq!(output.quality.check, CheckStatus::Verified);
+    assert!(output.quality.confidence < 1.0);
+
+    // A flipped parity bit
+    let damaged = flip(&binary, 10 + 2 * 5 + 4);
+    assert_eq!(decode(&damaged, &correcting), Ok("1234".to_string()));
+
+    // A flipped bit in the LRC itself
+    let damaged = flip(&binary, 10 + 6 * 5 + 2);
+    assert_eq!(
+        decode(&damaged, &DecodePolicy::standard()),
+        Err(DecoderError::LrcCheckFailed)
+    );
+    let bits: BitBuf = damaged.parse().unwrap();
+    let output = decoder.decode(bits.as_stream()).unwrap();
+    assert_eq!(output.data, "1234");
+    assert_eq!(output.metadata.corrected, [6]);
+
+    // Two damaged characters cannot be rebuilt
+    let damaged = flip(&flip(&binary, 10 + 5), 10 + 4 * 5);
+    assert_eq!(
+        decode(&damaged, &correcting),
+        Err(DecoderError::ParityError { position: 1 })
+    );
+    let lenient = correcting.with_placeholder('_');
+    assert_eq!(decode(&damaged, &lenient), Ok("_23_".to_string()));
+}
+
+/// Test correction of a Track 1 character
+#[test]
+fn test_track1_correction() {
+    let track1 = encode_track(Track::Track1, "DOE/J");
+    let binary = flip(&format!("{}{}", "0".repeat(9), track1), 9 + 4 * 7 + 3);
+
+    let formats = vec![Format::Track1];
+    let policy = DecodePolicy::strict().with_correction(true);
+    let decoder = Decoder::new(&formats).with_policy(policy);
+    let bits: BitBuf = binary.parse().unwrap();
+    let output = decoder.decode(bits.as_stream()).unwrap();
+    assert_eq!(output.data, "DOE/J");
+    assert_eq!(output.metadata.corrected, [4]);
+    let clean: BitBuf = format!("{}{}", "0".repeat(9), track1).parse().unwrap();
+    let clean = decoder.decode(clean.as_stream()).unwrap();
+    assert_eq!(output.metadata.characters, clean.metadata.characters);
+}
@@ -0,0 +1,123 @@
+#![allow(clippy::uninlined_format_args)]
+
+mod common;
+
+use common::{encode_track, with_clock};
+use magstripe_rs::{
+    Alphabet, BitBuf, BitStream, DecodePolicy, Decoder, DecoderError, Format, FormatSpec, Track,
+};
+
+/// Test that every successful format is returned, best first
+#[test]
+fn test_candidates_are_ranked() {
+    let bits: BitBuf = with_clock(&encode_track(Track::Track2, "5432"), 10)
+        .parse()
+        .unwrap();
+    let stream = bits.as_stream();
+
+    // A spec without parity or integrity checks that also reads the stream
+    let loose = FormatSpec::builder(5)
+        .start_sentinel(0b01011)
+        .alphabet(Alphabet::Table(
+            "0123456789ABCDEFGHIJKLMNOPQRSTUV".chars().collect(),
+        ))
+        .build()
+        .unwrap();
+    let formats = vec![
+        Format::Custom(loose.clone()),
+        Format::Track2Inverted,
+        Format::Track2,
+        Format::Track2LSB,
+    ];
+    let decoder = Decoder::new(&formats).with_reverse(false);
+
+    // The first match is the loose spec
+    let stream_copy = bits.as_stream();
+    assert_eq!(
+        decoder.decode(stream_copy).unwrap().format,
+        &Format::Custom(loose)
+    );
+
+    let ranked = decoder.decode_all(stream).unwrap();
+    let formats: Vec<&Format> = ranked.candidates.iter().map(|c| c.output.format).collect();
+    assert_eq!(formats[..2], [&Format::Track2, &Format::Track2LSB]);
+    assert_eq!(ranked.candidates.len(), 3);
+    assert_eq!(ranked.best().output.data, "5432");
+    assert_eq!(ranked.best().score, 18);
+    assert!(ranked.best().is_clean());
+    assert!(!ranked.candidates[2].is_clean());
+    assert!(ranked.candidates[2].score < ranked.best().score);
+    assert!(!ranked.ambiguous);
+
+    // The inverted format failed
+    assert_eq!(ranked.report.attempts.len(), 1);
+    assert_eq!(ranked.report.attempts[0].format, Format::Track2Inverted);
+}
+
+/// Test that two clean readings of different payloads are flagged
+#[test]
+fn test_ambiguous_candidates() {
+    let bits: BitBuf = with_clock(&encode_track(Track::Track2, "5432"), 10)
+        .parse()
+        .unwrap();
+    let stream = bits.as_stream();
+
+    // Track 2 framing and LRC, but a different character set
+    let relabelled = FormatSpec::track2()
+        .to_builder()
+        .alphabet(Alphabet::Offset {
+            base: 0x40,
+            range: 0..=0x0F,
+        })
+        .build()
+        .unwrap();
+    let formats = vec![Format::Track2, Format::Custom(relabelled)];
+    let ranked = Decoder::new(&formats).decode_all(stream).unwrap();
+
+    assert_eq!(ranked.candidates.len(), 2);
+    assert_eq!(ranked.candidates[1].output.data, "EDCB");
+    assert!(ranked.candidates.iter().all(|c| c.is_clean()));
+    assert!(ranked.ambiguous);
+}
+
+/// Test that a stream no format can decode still reports every failure
+#[test]
+fn test_no_candidates() {
+    let data = vec![0u8; 8];
+    let stream = BitStream::new(&data, 64).unwrap();
+    let formats = vec![Format::Track2, Format::Track1];
+
+    match Decoder::new(&formats).decode_all(stream) {
+        Err(DecoderError::NoValidFormat { attempted, report }) => {
+            assert_eq!(attempted, 2);
+            assert_eq!(report.attempts.len(), 4);
+        }
+        other => panic!("Expected NoValidFormat, got {:?}", other),
+    }
+}
+
+/// Test that a candidate with tolerated parity errors loses the parity points
+#[test]
+fn test_parity_errors_lower_score() {
+    let binary = with_clock(&encode_track(Track::Track2, "5432"), 10);
+    // Flip the parity bit of the first data character, leaving its data bits
+    // and so the LRC intact
+    let mut damaged: Vec<char> = binary.chars().collect();
+    let idx = 10 + 5 + 4;
+    damaged[idx] = if damaged[idx] == '1' { '0' } else { '1' };
+    let bits: BitBuf = damaged.into_iter().collect::<String>().parse().unwrap();
+
+    let formats = vec![Format::Track2];
+    let policy = DecodePolicy::standard()
+        .with_placeholder('?')
+        .with_correction(false);
+    let ranked = Decoder::new(&formats)
+        .with_policy(policy)
+        .decode_all(bits.as_stream())
+        .unwrap();
+    let best = ranked.best();
+    assert_eq!(best.output.data, "?432");
+    assert_eq!(best.output.quality.parity_failures, 1);
+    assert!(best.output.metadata.integrity_checked);
+    assert_eq!(best.score, 14);
+}
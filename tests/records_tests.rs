@@ -0,0 +1,156 @@
+mod common;
+
+use common::{char_codes, encode_codes, encode_track};
+use magstripe_rs::{
+    BitBuf, BitStream, DecodeMetadata, DecodePolicy, DecodedTrack, Decoder, DecoderError, Format,
+    Track, TrackDecoder,
+};
+
+/// Test that back to back records are all decoded with their bit ranges
+#[test]
+fn test_records_back_to_back() {
+    let first = encode_track(Track::Track2, "1234567890");
+    let second = encode_track(Track::Track2, "4111111111111111=2512");
+    let third = encode_track(Track::Track2, "99");
+    let clock = "0".repeat(12);
+    let binary = format!("{clock}{first}{clock}{second}{third}{clock}");
+    let bits: BitBuf = binary.parse().unwrap();
+
+    let formats = [Format::Track2];
+    let decoder = Decoder::new(&formats);
+    let records: Vec<_> = decoder.records(bits.as_stream()).collect();
+
+    let data: Vec<&str> = records.iter().map(|r| r.data.as_str()).collect();
+    assert_eq!(data, ["1234567890", "4111111111111111=2512", "99"]);
+
+    let second_start = 2 * clock.len() + first.len();
+    assert_eq!(records[0].metadata.start_sentinel_offset, Some(clock.len()));
+    assert_eq!(records[0].metadata.bit_range.end, clock.len() + first.len());
+    assert_eq!(
+        records[1].metadata.start_sentinel_offset,
+        Some(second_start)
+    );
+    assert_eq!(
+        records[1].metadata.bit_range.end,
+        second_start + second.len()
+    );
+    assert_eq!(
+        records[2].metadata.check_offset,
+        Some(second_start + second.len() + third.len() - 5)
+    );
+    assert_eq!(records[1].metadata.characters[1].offset, second_start + 5);
+
+    assert!(decoder.leftovers(bits.as_stream()).is_empty());
+}
+
+/// Test that a damaged record is skipped and reported as left over
+#[test]
+fn test_records_skip_damaged_record() {
+    let good = encode_track(Track::Track2, "1234");
+    // A start sentinel followed by characters with no end sentinel or LRC
+    let damaged = format!(
+        "{}{}",
+        encode_codes(&char_codes(Track::Track2, ";5678"), Track::Track2),
+        "11011"
+    );
+    let clock = "0".repeat(12);
+    let binary = format!("{clock}{good}{clock}{damaged}{clock}{good}{clock}");
+    let bits: BitBuf = binary.parse().unwrap();
+
+    let formats = [Format::Track2];
+    let decoder = Decoder::new(&formats);
+    let stream = bits.as_stream();
+    let data: Vec<String> = decoder.records(stream.clone()).map(|r| r.data).collect();
+    assert_eq!(data, ["1234", "1234"]);
+
+    let damaged_start = 2 * clock.len() + good.len();
+    let leftovers = decoder.leftovers(stream);
+    assert_eq!(leftovers.len(), 1);
+    assert_eq!(leftovers[0], damaged_start..damaged_start + damaged.len());
+}
+
+/// Test a stream with no records
+#[test]
+fn test_records_none() {
+    let formats = [Format::Track2];
+    let decoder = Decoder::new(&formats);
+    let blank = [0u8; 8];
+    assert_eq!(
+        decoder.records(BitStream::new(&blank, 64).unwrap()).count(),
+        0
+    );
+    assert!(decoder
+        .leftovers(BitStream::new(&blank, 64).unwrap())
+        .is_empty());
+
+    let noise = [0xA5u8; 4];
+    let leftovers = decoder.leftovers(BitStream::new(&noise, 32).unwrap());
+    assert_eq!(leftovers.len(), 1);
+    assert_eq!(leftovers[0], 0..32);
+}
+
+/// Test that junk before the clock bits of the first record is left over
+/// whole, whichever way the format clocks
+#[test]
+fn test_records_leading_junk() {
+    let record = encode_track(Track::Track2, "1234");
+    let clock = "0".repeat(20);
+    // Junk bits with the clock value at either end run into the clock bits
+    for (junk, expected) in [("11", 0..2), ("1011", 0..4), ("0110", 1..3)] {
+        let binary = format!("{junk}{clock}{record}{clock}");
+        let bits: BitBuf = binary.parse().unwrap();
+        let formats = [Format::Track2];
+        let decoder = Decoder::new(&formats);
+        assert_eq!(
+            decoder.leftovers(bits.as_stream()),
+            vec![expected.clone()],
+            "junk {junk}"
+        );
+
+        let mut inverted = bits.clone();
+        inverted.invert();
+        let formats = [Format::Track2Inverted];
+        let decoder = Decoder::new(&formats);
+        assert_eq!(
+            decoder.leftovers(inverted.as_stream()),
+            vec![expected],
+            "inverted junk {junk}"
+        );
+    }
+}
+
+/// A user decoder that accepts anything without saying where it was found
+#[derive(Debug)]
+struct Anywhere;
+
+impl TrackDecoder for Anywhere {
+    fn decode(
+        &self,
+        _stream: &BitStream,
+        _policy: &DecodePolicy,
+    ) -> Result<DecodedTrack, DecoderError> {
+        Ok(DecodedTrack::new("1", DecodeMetadata::default()))
+    }
+}
+
+/// Test that a decode with an empty bit range is skipped rather than
+/// decoded again at the same offset forever
+#[test]
+fn test_records_skip_empty_bit_range() {
+    let clock = "0".repeat(12);
+    let binary = format!("{clock}{}{clock}", encode_track(Track::Track2, "1234"));
+    let bits: BitBuf = binary.parse().unwrap();
+
+    let formats = [Format::user(Anywhere)];
+    let decoder = Decoder::new(&formats);
+    assert_eq!(decoder.records(bits.as_stream()).count(), 0);
+    assert_eq!(decoder.leftovers(bits.as_stream()).len(), 1);
+
+    let formats = [Format::user(Anywhere), Format::Track2];
+    let decoder = Decoder::new(&formats);
+    let records: Vec<_> = decoder.records(bits.as_stream()).collect();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].format, &formats[1]);
+    assert_eq!(records[0].data, "1234");
+    assert!(decoder.leftovers(bits.as_stream()).is_empty());
+}
@@ -0,0 +1,286 @@
+#![allow(clippy::uninlined_format_args)]
+
+mod common;
+
+use common::{codes_to_binary, CARD, CARD_BITS};
+use magstripe_rs::{
+    BitBuf, BitStream, BitStreamError, CharacterKind, Decoder, DecoderError, DecoderOutput, Format,
+    StreamEvent, StreamingDecoder,
+};
+
+/// Feed a binary string to a streaming decoder `chunk` bits at a time
+fn stream_binary<'a>(
+    streaming: &mut StreamingDecoder<'a>,
+    binary: &str,
+    chunk: usize,
+) -> Vec<StreamEvent<'a>> {
+    let binary: Vec<char> = binary.chars().collect();
+    let mut events = Vec::new();
+    for bits in binary.chunks(chunk) {
+        let bits: BitBuf = bits.iter().collect::<String>().parse().unwrap();
+        events.extend(streaming.push_bits(bits.as_bytes(), bits.len()).unwrap());
+    }
+    events
+}
+
+/// `;123?` and its LRC, with odd parity in bit 4
+const TRACK2_123: [u8; 6] = [0b01011, 0b00001, 0b00010, 0b10011, 0b11111, 0b00100];
+
+/// Test the events of a clean swipe fed a few bits at a time
+#[test]
+fn test_streaming_events() {
+    let binary = format!(
+        "{}{}{}",
+        "0".repeat(12),
+        codes_to_binary(&TRACK2_123, 5),
+        "0".repeat(9)
+    );
+    let formats = [Format::Track2];
+    let mut streaming = StreamingDecoder::new(Decoder::new(&formats));
+
+    let events = stream_binary(&mut streaming, &binary, 3);
+    assert_eq!(
+        events,
+        vec![
+            StreamEvent::StartSentinel {
+                format: &formats[0],
+                offset: 12
+            },
+            StreamEvent::Character {
+                position: 1,
+                character: '1',
+                offset: 17
+            },
+            StreamEvent::Character {
+                position: 2,
+                character: '2',
+                offset: 22
+            },
+            StreamEvent::Character {
+                position: 3,
+                character: '3',
+                offset: 27
+            },
+            StreamEvent::EndSentinel { offset: 32 },
+            StreamEvent::CheckVerified { offset: 37 },
+            StreamEvent::SwipeComplete,
+        ]
+    );
+    assert!(streaming.is_complete());
+    assert_eq!(streaming.len(), binary.len());
+    assert_eq!(streaming.finish().unwrap().data, "123");
+}
+
+/// The format locked onto last and the characters reported for it
+fn final_lock<'a>(events: &[StreamEvent<'a>]) -> (Option<&'a Format>, Vec<(usize, char, usize)>) {
+    let Some(start) = events
+        .iter()
+        .rposition(|event| matches!(event, StreamEvent::StartSentinel { .. }))
+    else {
+        return (None, Vec::new());
+    };
+    let StreamEvent::StartSentinel { format, .. } = events[start] else {
+        unreachable!()
+    };
+    assert!(!events[start..]
+        .iter()
+        .any(|event| matches!(event, StreamEvent::LockLost { .. })));
+    let characters = events[start..]
+        .iter()
+        .filter_map(|event| match *event {
+            StreamEvent::Character {
+                position,
+                character,
+                offset,
+            } => Some((position, character, offset)),
+            _ => None,
+        })
+        .collect();
+    (Some(format), characters)
+}
+
+/// Assert that the events of a complete swipe describe the batch decode of
+/// the same bits
+fn assert_events_match(events: &[StreamEvent], batch: &DecoderOutput, context: &str) {
+    let expected: Vec<_> = batch
+        .metadata
+        .characters
+        .iter()
+        .enumerate()
+        .filter_map(|(position, info)| match info.kind {
+            CharacterKind::Data(character) => Some((position, character, info.offset)),
+            _ => None,
+        })
+        .collect();
+    let (format, characters) = final_lock(events);
+    assert_eq!(format, Some(batch.format), "{}", context);
+    assert_eq!(characters, expected, "{}", context);
+    if batch.metadata.integrity_checked {
+        let offset = batch.metadata.check_offset.unwrap();
+        assert!(
+            events.contains(&StreamEvent::CheckVerified { offset }),
+            "{}",
+            context
+        );
+    }
+    assert_eq!(
+        events.last(),
+        Some(&StreamEvent::SwipeComplete),
+        "{}",
+        context
+    );
+}
+
+/// Test that the events follow the batch decode, whatever the chunk size
+#[test]
+fn test_streaming_matches_batch() {
+    let mut bits = BitBuf::from(BitStream::new(&CARD, CARD_BITS).unwrap());
+
+    let formats = [Format::Track2, Format::Track2Inverted, Format::Track1];
+    let decoder = Decoder::new(&formats);
+    let forward = decoder.clone().with_reverse(false);
+
+    // As swiped, then backwards
+    for _ in 0..2 {
+        let binary = bits.to_string();
+        let batch = decoder.decode(bits.as_stream());
+        let forward_batch = forward.decode(bits.as_stream());
+
+        for chunk in 1..=17 {
+            let context = format!("chunk size {}", chunk);
+            let mut streaming = StreamingDecoder::new(decoder.clone());
+            let events = stream_binary(&mut streaming, &binary, chunk);
+            assert_eq!(streaming.stream(), bits.as_stream());
+            assert_eq!(streaming.finish(), batch, "{}", context);
+
+            // Events only follow the stream forwards
+            match &forward_batch {
+                Ok(output) => assert_events_match(&events, output, &context),
+                Err(_) => assert!(!events.contains(&StreamEvent::SwipeComplete)),
+            }
+        }
+        bits.reverse();
+    }
+}
+
+/// Test that a false start sentinel does not hide the swipe of a later format
+#[test]
+fn test_streaming_false_start_sentinel() {
+    // A `%` that leads nowhere, then `;1?` and its LRC
+    let bits: BitBuf = "1010001 1111111 0000000000 11010 10000 11111 10101 0000000000"
+        .parse()
+        .unwrap();
+    let formats = [Format::Track2, Format::Track1];
+    let decoder = Decoder::new(&formats);
+    let batch = decoder.decode(bits.as_stream()).unwrap();
+    assert_eq!(batch.data, "1");
+    assert_eq!(batch.format, &Format::Track2);
+
+    for chunk in [1, 3, 8] {
+        let context = format!("chunk size {}", chunk);
+        let mut streaming = StreamingDecoder::new(decoder.clone());
+        let events = stream_binary(&mut streaming, &bits.to_string(), chunk);
+
+        assert_eq!(
+            events[0],
+            StreamEvent::StartSentinel {
+                format: &formats[1],
+                offset: 0
+            },
+            "{}",
+            context
+        );
+        assert!(events.iter().any(|event| matches!(
+            event,
+            StreamEvent::LockLost {
+                format: Format::Track1,
+                error: Some(_)
+            }
+        )));
+        assert_events_match(&events, &batch, &context);
+        assert!(streaming.is_complete());
+        assert_eq!(streaming.finish(), Ok(batch.clone()));
+    }
+}
+
+/// Test that a format earlier in the list takes over once its start sentinel
+/// arrives
+#[test]
+fn test_streaming_preempted_lock() {
+    // A `%` right before the Track 2 start sentinel
+    let bits: BitBuf = "0000000000 1010001 11010 10000 11111 10101 0000000000"
+        .parse()
+        .unwrap();
+    let formats = [Format::Track2, Format::Track1];
+    let batch = Decoder::new(&formats).decode(bits.as_stream()).unwrap();
+    assert_eq!(batch.format, &Format::Track2);
+    let mut streaming = StreamingDecoder::new(Decoder::new(&formats));
+    let events = stream_binary(&mut streaming, &bits.to_string(), 1);
+    assert!(events.contains(&StreamEvent::LockLost {
+        format: &formats[1],
+        error: None
+    }));
+    assert_events_match(&events, &batch, "preempted");
+}
+
+/// Test that a bad LRC and a bad character are reported as they arrive
+#[test]
+fn test_streaming_errors() {
+    let mut bad_lrc = TRACK2_123;
+    bad_lrc[5] = 0b10101;
+    let binary = format!("{}{}", "0".repeat(10), codes_to_binary(&bad_lrc, 5));
+    let formats = [Format::Track2];
+    let mut streaming = StreamingDecoder::new(Decoder::new(&formats));
+
+    let events = stream_binary(&mut streaming, &binary, 8);
+    assert_eq!(
+        &events[events.len() - 2..],
+        &[
+            StreamEvent::CheckFailed {
+                offset: 35,
+                error: DecoderError::LrcCheckFailed
+            },
+            StreamEvent::LockLost {
+                format: &formats[0],
+                error: Some(DecoderError::LrcCheckFailed)
+            },
+        ]
+    );
+    assert!(streaming.is_complete());
+
+    let mut bad_char = TRACK2_123;
+    bad_char[2] ^= 0b00100;
+    let binary = format!("{}{}", "0".repeat(10), codes_to_binary(&bad_char, 5));
+    let mut streaming = StreamingDecoder::new(Decoder::new(&formats));
+
+    let events = stream_binary(&mut streaming, &binary, 8);
+    assert_eq!(
+        &events[events.len() - 2..],
+        &[
+            StreamEvent::CharacterError {
+                offset: 20,
+                error: DecoderError::ParityError { position: 2 }
+            },
+            StreamEvent::LockLost {
+                format: &formats[0],
+                error: Some(DecoderError::ParityError { position: 2 })
+            },
+        ]
+    );
+    assert!(streaming.is_complete());
+    assert!(streaming.finish().is_err());
+}
+
+/// Test that a chunk too small for its bit count is rejected
+#[test]
+fn test_streaming_buffer_too_small() {
+    let mut streaming = StreamingDecoder::new(Decoder::default());
+    assert_eq!(
+        streaming.push_bits(&[0xFF], 9),
+        Err(BitStreamError::BufferTooSmall {
+            required_bytes: 2,
+            provided_bytes: 1
+        })
+    );
+    assert!(streaming.is_empty());
+}
@@ -0,0 +1,138 @@
+#![allow(clippy::uninlined_format_args)]
+
+mod common;
+
+use common::encode_track;
+use magstripe_rs::{
+    Alphabet, BitBuf, BitStream, Decoder, DecoderError, Format, FormatSpec, Integrity, ParityType,
+    Track,
+};
+
+/// Track 1 data of a test card, without sentinels, encoded by `encode_track`
+/// into the synthetic swipes below
+const TRACK1_DATA: &str = "B4111111111111111^CARDHOLDER/TEST^2512101000000000000";
+
+/// `TRACK1_DATA` laid out as an inverting reader would deliver it: 21 clock
+/// bits, the track and 30 clock bits, all inverted and packed MSB-first, 443
+/// bits in all.
+///
+/// This is synthetic, produced by `encode_track`, not a reader capture. It
+/// stands in for one until a real Track 1 capture with an odd-length
+/// preamble is on file, and only shows that the byte-level path handles the
+/// layout of the Track 2 captures.
+const SYNTHETIC_TRACK1_PAYLOAD: [u8; 56] = [
+    255, 255, 250, 235, 154, 157, 58, 116, 233, 211, 167, 78, 157, 58, 116, 233, 211, 167, 80, 78,
+    188, 179, 179, 160, 92, 187, 22, 217, 13, 165, 105, 141, 48, 109, 43, 117, 105, 215, 183, 94,
+    253, 251, 247, 239, 223, 191, 126, 253, 251, 246, 15, 7, 255, 255, 255, 224,
+];
+
+/// Helper function to invert every bit of a binary string
+fn invert(binary: &str) -> String {
+    binary
+        .chars()
+        .map(|c| if c == '1' { '0' } else { '1' })
+        .collect()
+}
+
+/// Synthetic Track 1 reader buffer with an odd-length run of inverted clock
+/// bits
+#[test]
+fn test_track1_synthetic_reader_payload() {
+    let stream = BitStream::new(&SYNTHETIC_TRACK1_PAYLOAD, 443).unwrap();
+    let formats = vec![Format::Track1, Format::Track1Inverted];
+    let output = Decoder::new(&formats).decode(stream).unwrap();
+    assert_eq!(output.data, TRACK1_DATA);
+    assert_eq!(output.format, &Format::Track1Inverted);
+    assert_eq!(output.metadata.start_sentinel_offset, Some(21));
+    assert!(output.metadata.integrity_checked);
+
+    // The padding in the final byte is found without the bit count
+    assert_eq!(BitStream::from_raw(&SYNTHETIC_TRACK1_PAYLOAD).len(), 443);
+}
+
+/// Track 1 swipe whose leading clock zeros are not a multiple of 7 bits
+#[test]
+fn test_track1_odd_length_preamble() {
+    for preamble in [13, 20, 29] {
+        let binary = format!(
+            "{}{}{}",
+            "0".repeat(preamble),
+            encode_track(Track::Track1, TRACK1_DATA),
+            "0".repeat(24)
+        );
+        let bits: BitBuf = binary.parse().unwrap();
+        let stream = bits.as_stream();
+
+        let output = Decoder::new(&[Format::Track1]).decode(stream).unwrap();
+        assert_eq!(output.data, TRACK1_DATA, "preamble of {} bits", preamble);
+    }
+}
+
+/// Inverted Track 1 swipe with an odd-length run of leading ones
+#[test]
+fn test_track1_inverted_odd_length_preamble() {
+    let binary = invert(&format!(
+        "{}{}{}",
+        "0".repeat(17),
+        encode_track(Track::Track1, TRACK1_DATA),
+        "0".repeat(19)
+    ));
+    let bits: BitBuf = binary.parse().unwrap();
+    let stream = bits.as_stream();
+
+    let formats = vec![Format::Track1, Format::Track1Inverted];
+    let output = Decoder::new(&formats).decode(stream).unwrap();
+    assert_eq!(output.data, TRACK1_DATA);
+    assert!(matches!(output.format, Format::Track1Inverted));
+}
+
+/// A corrupted LRC is reported once the sentinels are found
+#[test]
+fn test_track1_lrc_mismatch() {
+    let mut encoded = encode_track(Track::Track1, TRACK1_DATA);
+    // Flip the first data bit of the LRC character
+    let lrc_start = encoded.len() - 7;
+    let flipped = if &encoded[lrc_start..=lrc_start] == "1" {
+        "0"
+    } else {
+        "1"
+    };
+    encoded.replace_range(lrc_start..=lrc_start, flipped);
+
+    let binary = format!("{}{}{}", "0".repeat(11), encoded, "0".repeat(16));
+    let bits: BitBuf = binary.parse().unwrap();
+    let stream = bits.as_stream();
+
+    let result = magstripe_rs::decoder::decode_track1(&stream, false);
+    assert_eq!(result, Err(DecoderError::LrcCheckFailed));
+}
+
+/// Custom formats synchronise on the start sentinel at any bit offset
+#[test]
+fn test_custom_format_bit_sync() {
+    let spec = FormatSpec {
+        bits_per_char: 7,
+        start_sentinel: Some(0b1000101), // '%' with odd parity
+        end_sentinel: Some(0b0011111),   // '?' with odd parity
+        lsb_first: true,
+        parity: ParityType::Odd,
+        inverted: false,
+        parity_position: None,
+        integrity: Integrity::None,
+        alphabet: Alphabet::Standard,
+        max_length: None,
+    };
+
+    let binary = format!(
+        "{}{}{}",
+        "0".repeat(9),
+        encode_track(Track::Track1, TRACK1_DATA),
+        "0".repeat(12)
+    );
+    let bits: BitBuf = binary.parse().unwrap();
+    let stream = bits.as_stream();
+
+    let formats = vec![Format::Custom(spec)];
+    let output = Decoder::new(&formats).decode(stream).unwrap();
+    assert_eq!(output.data, TRACK1_DATA);
+}
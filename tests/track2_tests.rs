@@ -1,6 +1,26 @@
-use magstripe_rs::{decoder::decode_track2, BitStream};
+mod common;
+
+use common::encode_track_with;
+use magstripe_rs::{
+    decoder::decode_track2, Alphabet, BitBuf, BitStream, Decoder, Encoding, Format, FormatSpec,
+    Integrity, ParityType, Track,
+};
 use tracing::debug;
 
+/// Helper function to encode `;data?` plus LRC as Track 2 characters with the
+/// odd parity bit written first, as legacy encoders do, packed into bytes
+fn encode_parity_first(data: &str, leading_zeros: usize) -> (Vec<u8>, usize) {
+    let encoding = Encoding::default().with_parity_position(0);
+    let binary = format!(
+        "{}{}{}",
+        "0".repeat(leading_zeros),
+        encode_track_with(Track::Track2, data, &encoding),
+        "0".repeat(10)
+    );
+    let bits: BitBuf = binary.parse().unwrap();
+    (bits.as_bytes().to_vec(), bits.len())
+}
+
 #[test_log::test]
 fn test_decode_track2_weird() {
     // We encountered this payload in the Academy Building, Park West off of a vanderbilt nfc reader
@@ -39,3 +59,51 @@ fn test_decode_track2_dogpatch_weird(){
     let decoded = decode_track2(&stream, false, true, false, false, false);
     panic!("========= Decoded: {decoded:?}");
 }
+
+#[test_log::test]
+fn test_decode_track2_parity_first() {
+    let (payload, bit_count) = encode_parity_first("5412750000001234", 14);
+    let stream = BitStream::new(&payload, bit_count).unwrap();
+
+    // Standard Track 2 cannot make sense of the relocated parity bit
+    assert!(decode_track2(&stream, false, true, false, false, false).is_err());
+
+    let decoded = decode_track2(&stream, false, true, false, true, false);
+    assert_eq!(decoded, Ok("5412750000001234".to_string()));
+
+    let output = Decoder::new(&[Format::Track2SwappedParity])
+        .decode(stream)
+        .unwrap();
+    assert_eq!(output.data, "5412750000001234");
+}
+
+#[test_log::test]
+fn test_decode_custom_parity_position() {
+    let (payload, bit_count) = encode_parity_first("0004048712", 9);
+    let stream = BitStream::new(&payload, bit_count).unwrap();
+
+    // ';' and '?' with the odd parity bit in bit 0
+    let spec = FormatSpec {
+        bits_per_char: 5,
+        start_sentinel: Some(0b10110),
+        end_sentinel: Some(0b11111),
+        lsb_first: true,
+        parity: ParityType::Odd,
+        inverted: false,
+        parity_position: Some(0),
+        integrity: Integrity::None,
+        alphabet: Alphabet::Standard,
+        max_length: None,
+    };
+
+    let formats = vec![Format::Custom(spec.clone())];
+    let output = Decoder::new(&formats).decode(stream).unwrap();
+    assert_eq!(output.data, "0004048712");
+
+    let invalid = vec![Format::Custom(FormatSpec {
+        parity_position: Some(5),
+        ..spec
+    })];
+    let stream = BitStream::new(&payload, bit_count).unwrap();
+    assert!(magstripe_rs::decoder::decode_with_formats(&invalid, stream).is_err());
+}
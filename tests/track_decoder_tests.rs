@@ -0,0 +1,138 @@
+mod common;
+
+use common::codes_to_binary;
+use magstripe_rs::decoder::common::{calculate_lrc, check_parity, read_char, strip_parity};
+use magstripe_rs::{
+    BitBuf, BitStream, CharacterInfo, CharacterKind, CheckStatus, DecodeMetadata, DecodePolicy,
+    DecodedTrack, Decoder, DecoderError, Format, ParityType, SwipeDirection, TrackDecoder,
+};
+
+/// A fixed-length record of 5-bit digits with odd parity and no sentinels,
+/// followed by an LRC
+#[derive(Debug)]
+struct FixedDigits {
+    length: usize,
+}
+
+impl TrackDecoder for FixedDigits {
+    fn decode(
+        &self,
+        stream: &BitStream,
+        _policy: &DecodePolicy,
+    ) -> Result<DecodedTrack, DecoderError> {
+        let mut metadata = DecodeMetadata {
+            parity_checked: true,
+            ..DecodeMetadata::default()
+        };
+        let mut digits = Vec::new();
+        let mut data = String::new();
+        for (position, offset) in (0..=self.length).map(|i| (i, i * 5)) {
+            let code = read_char(stream, offset, 5, true, false).ok_or(
+                DecoderError::BitstreamTooShort {
+                    bit_count: stream.len(),
+                    minimum_required: (self.length + 1) * 5,
+                },
+            )?;
+            if !check_parity(code, 5, &ParityType::Odd) {
+                return Err(DecoderError::ParityError { position });
+            }
+            let kind = if position == self.length {
+                if calculate_lrc(&digits) != strip_parity(code, 4) {
+                    return Err(DecoderError::LrcCheckFailed);
+                }
+                metadata.check_offset = Some(offset);
+                metadata.integrity_present = true;
+                metadata.integrity_checked = true;
+                CharacterKind::Check
+            } else {
+                let character = char::from(b'0' + strip_parity(code, 4));
+                digits.push(strip_parity(code, 4));
+                data.push(character);
+                CharacterKind::Data(character)
+            };
+            metadata
+                .characters
+                .push(CharacterInfo { offset, code, kind });
+        }
+        metadata.bit_range = 0..(self.length + 1) * 5;
+        metadata.trailing_clock_bits = stream.len() - metadata.bit_range.end;
+        Ok(DecodedTrack::new(data, metadata))
+    }
+}
+
+/// `123` and its LRC, with odd parity in bit 4
+const DIGITS_123: [u8; 4] = [0b00001, 0b00010, 0b10011, 0b10000];
+
+/// Test a user decoder mixed with built-in formats
+#[test]
+fn test_user_decoder_in_format_list() {
+    let binary = format!("{}{}", codes_to_binary(&DIGITS_123, 5), "0".repeat(10));
+    let bits: BitBuf = binary.parse().unwrap();
+    let stream = bits.as_stream();
+
+    let formats = vec![Format::Track2, Format::user(FixedDigits { length: 3 })];
+    let (result, report) = Decoder::new(&formats)
+        .with_reverse(false)
+        .decode_with_report(stream);
+    let output = result.unwrap();
+    assert_eq!(output.data, "123");
+    assert_eq!(output.bytes, b"123");
+    assert_eq!(output.format, &formats[1]);
+    assert_eq!(output.quality.check, CheckStatus::Verified);
+    assert_eq!(report.attempts.len(), 1);
+    assert_eq!(report.attempts[0].format, Format::Track2);
+
+    // Failures of user decoders are reported like any other
+    let short = bits.slice(0..15);
+    let stream = short.as_stream();
+    let formats = vec![Format::from(
+        Box::new(FixedDigits { length: 3 }) as Box<dyn TrackDecoder>
+    )];
+    let result = Decoder::new(&formats).with_reverse(false).decode(stream);
+    let Err(DecoderError::NoValidFormat { report, .. }) = result else {
+        panic!("expected NoValidFormat, got {:?}", result);
+    };
+    assert_eq!(report.attempts_for(&formats[0]).count(), 1);
+    assert!(matches!(
+        report.attempts[0].error,
+        DecoderError::BitstreamTooShort { .. }
+    ));
+}
+
+/// Test that user decoders see reversed swipes and are ranked by their metadata
+#[test]
+fn test_user_decoder_reversed_and_ranked() {
+    let binary: String = format!("{}{}", codes_to_binary(&DIGITS_123, 5), "0".repeat(10))
+        .chars()
+        .rev()
+        .collect();
+    let bits: BitBuf = binary.parse().unwrap();
+    let stream = bits.as_stream();
+
+    let formats = vec![
+        Format::Track2Raw,
+        Format::user(FixedDigits { length: 3 }),
+        Format::user(FixedDigits { length: 2 }),
+    ];
+    let ranked = Decoder::new(&formats).decode_all(stream).unwrap();
+    let best = ranked.best();
+    assert_eq!(best.output.format, &formats[1]);
+    assert_eq!(best.output.direction, SwipeDirection::Reverse);
+    assert_eq!(best.score, 8 + 4 + 1);
+    assert!(best.is_clean());
+    assert_ne!(formats[1], formats[2]);
+}
+
+/// Test that metadata counting clock bits outside its bit range is assessed
+/// without overflowing
+#[test]
+fn test_user_metadata_clock_bits_before_range() {
+    let metadata = DecodeMetadata {
+        leading_clock_bits: 5,
+        ..Default::default()
+    };
+    let track = DecodedTrack::new("1", metadata);
+    assert_eq!(track.quality.sentinel_skew, 0);
+    assert_eq!(track.quality.leading_clock_bits, 5);
+    assert!(track.quality.confidence > 0.0);
+}